@@ -0,0 +1,460 @@
+use cosmwasm_std::{to_binary, Addr, Order, Response, StdResult, Storage, Uint128, WasmMsg};
+use cw20::{Cw20CoinVerified, Cw20ExecuteMsg};
+use cw_storage_plus::{Bound, Item, Map, U64Key};
+
+use crate::{
+    msg::MemberResponse,
+    state::{BoardroomInfo, Member, Snapshot},
+    ContractError,
+};
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+pub struct Boardroom<'a> {
+    info: Item<'a, BoardroomInfo>,
+    member: Map<'a, &'a Addr, Member>,
+    snapshots: Map<'a, U64Key, Snapshot>,
+}
+
+impl<'a> Boardroom<'a> {
+    pub const fn new() -> Self {
+        Self {
+            info: Item::new("boardroom"),
+            member: Map::new("member"),
+            snapshots: Map::new("snapshots"),
+        }
+    }
+
+    pub fn initialize(
+        &self,
+        storage: &mut dyn Storage,
+        share_token: Addr,
+        synth_token: Addr,
+        pool: Addr,
+        withdraw_lockup_epochs: u64,
+    ) -> StdResult<()> {
+        let info = BoardroomInfo {
+            share_token,
+            synth_token,
+            pool,
+            withdraw_lockup_epochs,
+            epoch: 0,
+            pool_epoch: 0,
+            total_staked: Uint128::zero(),
+            reward_per_share: Uint128::zero(),
+            undistributed: Uint128::zero(),
+            total_allocated: Uint128::zero(),
+        };
+
+        self.info.save(storage, &info)
+    }
+
+    pub fn deposit(
+        &self,
+        storage: &mut dyn Storage,
+        sender: &Addr,
+        coin: Cw20CoinVerified,
+    ) -> Result<Response, ContractError> {
+        let mut info = self.get_info(storage)?;
+        let mut member = self.get_member(storage, sender)?;
+
+        if info.share_token != coin.address {
+            return Err(ContractError::InvalidDepositToken {
+                want: info.share_token,
+                sent: coin.address,
+            });
+        }
+        if coin.amount.is_zero() {
+            return Err(ContractError::EmptyAmount {});
+        }
+
+        info.deposit(&mut member, coin.amount);
+
+        self.member.save(storage, sender, &member)?;
+        self.info.save(storage, &info)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "deposit")
+            .add_attribute("amount", coin.amount)
+            .add_attribute("unlock_epoch", info.unlock_epoch(&member).to_string()))
+    }
+
+    pub fn withdraw(
+        &self,
+        storage: &mut dyn Storage,
+        sender: &Addr,
+        amount: Uint128,
+    ) -> Result<Response, ContractError> {
+        let mut info = self.get_info(storage)?;
+        let mut member = self.get_member(storage, sender)?;
+
+        if amount.is_zero() {
+            return Err(ContractError::EmptyAmount {});
+        }
+        if amount > member.balance {
+            return Err(ContractError::WithdrawTooMuch {});
+        }
+        let unlock_epoch = info.unlock_epoch(&member);
+        if info.epoch < unlock_epoch {
+            return Err(ContractError::WithdrawLocked {
+                epoch: unlock_epoch,
+            });
+        }
+
+        info.withdraw(&mut member, amount);
+
+        self.member.save(storage, sender, &member)?;
+        self.info.save(storage, &info)?;
+
+        let msg = WasmMsg::Execute {
+            contract_addr: info.share_token.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: sender.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        };
+
+        Ok(Response::new()
+            .add_attribute("action", "withdraw")
+            .add_attribute("amount", amount)
+            .add_message(msg))
+    }
+
+    pub fn claim_reward(
+        &self,
+        storage: &mut dyn Storage,
+        sender: &Addr,
+    ) -> Result<Response, ContractError> {
+        let info = self.get_info(storage)?;
+        let mut member = self.get_member(storage, sender)?;
+
+        let reward = info.claim(&mut member);
+        if reward.is_zero() {
+            return Err(ContractError::NothingToClaim {});
+        }
+
+        self.member.save(storage, sender, &member)?;
+
+        let msg = WasmMsg::Execute {
+            contract_addr: info.synth_token.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: sender.to_string(),
+                amount: reward,
+            })?,
+            funds: vec![],
+        };
+
+        Ok(Response::new()
+            .add_attribute("action", "claim_reward")
+            .add_attribute("reward", reward)
+            .add_message(msg))
+    }
+
+    /// record the seigniorage of pool `epoch`, each pool epoch is allocated once.
+    /// Allow from pool only
+    pub fn allocate_seigniorage(
+        &self,
+        storage: &mut dyn Storage,
+        sender: &Addr,
+        epoch: u64,
+        amount: Uint128,
+        now: u64,
+    ) -> Result<Response, ContractError> {
+        let mut info = self.get_info(storage)?;
+
+        if sender != &info.pool {
+            return Err(ContractError::Unauthorized {});
+        }
+        if epoch <= info.pool_epoch {
+            return Err(ContractError::EpochAlreadyAllocated { epoch });
+        }
+
+        let snapshot = info.allocate(epoch, amount, now);
+
+        self.snapshots
+            .save(storage, U64Key::new(snapshot.epoch), &snapshot)?;
+        self.info.save(storage, &info)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "allocate_seigniorage")
+            .add_attribute("epoch", snapshot.epoch.to_string())
+            .add_attribute("pool_epoch", epoch.to_string())
+            .add_attribute("amount", amount)
+            .add_attribute("reward_per_share", info.reward_per_share))
+    }
+
+    pub fn set_withdraw_lockup(
+        &self,
+        storage: &mut dyn Storage,
+        withdraw_lockup_epochs: u64,
+    ) -> Result<Response, ContractError> {
+        self.info.update(storage, |mut info| -> StdResult<_> {
+            info.withdraw_lockup_epochs = withdraw_lockup_epochs;
+            Ok(info)
+        })?;
+
+        Ok(Response::new()
+            .add_attribute("action", "set_withdraw_lockup")
+            .add_attribute("withdraw_lockup_epochs", withdraw_lockup_epochs.to_string()))
+    }
+
+    /// the new pool counts its epochs from scratch
+    pub fn set_pool(
+        &self,
+        storage: &mut dyn Storage,
+        pool: Addr,
+    ) -> Result<Response, ContractError> {
+        self.info.update(storage, |mut info| -> StdResult<_> {
+            info.pool = pool.clone();
+            info.pool_epoch = 0;
+            Ok(info)
+        })?;
+
+        Ok(Response::new()
+            .add_attribute("action", "set_pool")
+            .add_attribute("pool", pool))
+    }
+
+    pub fn get_info(&self, storage: &dyn Storage) -> StdResult<BoardroomInfo> {
+        self.info.load(storage)
+    }
+
+    pub fn query_member(&self, storage: &dyn Storage, address: &Addr) -> StdResult<MemberResponse> {
+        let info = self.get_info(storage)?;
+        let member = self.get_member(storage, address)?;
+        Ok(MemberResponse {
+            balance: member.balance,
+            earned: info.earned(&member),
+            epoch_timer_start: member.epoch_timer_start,
+            unlock_epoch: info.unlock_epoch(&member),
+        })
+    }
+
+    /// epoch snapshots in ascending order
+    pub fn get_snapshots(
+        &self,
+        storage: &dyn Storage,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<Vec<Snapshot>> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after.map(Bound::exclusive_int);
+        self.snapshots
+            .range(storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|item| item.map(|(_, snapshot)| snapshot))
+            .collect()
+    }
+
+    fn get_member(&self, storage: &dyn Storage, address: &Addr) -> StdResult<Member> {
+        self.member
+            .may_load(storage, address)
+            .map(|x| x.unwrap_or_default())
+    }
+}
+
+pub const BOARDROOM: Boardroom = Boardroom::new();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    fn setup(storage: &mut dyn Storage, withdraw_lockup_epochs: u64) {
+        BOARDROOM
+            .initialize(
+                storage,
+                Addr::unchecked("share"),
+                Addr::unchecked("synth"),
+                Addr::unchecked("pool"),
+                withdraw_lockup_epochs,
+            )
+            .unwrap();
+    }
+
+    fn share(amount: u128) -> Cw20CoinVerified {
+        Cw20CoinVerified {
+            address: Addr::unchecked("share"),
+            amount: Uint128::new(amount),
+        }
+    }
+
+    fn allocate(storage: &mut dyn Storage, epoch: u64, amount: u128) {
+        BOARDROOM
+            .allocate_seigniorage(
+                storage,
+                &Addr::unchecked("pool"),
+                epoch,
+                Uint128::new(amount),
+                epoch * 100,
+            )
+            .unwrap();
+    }
+
+    fn earned(storage: &dyn Storage, address: &str) -> Uint128 {
+        BOARDROOM
+            .query_member(storage, &Addr::unchecked(address))
+            .unwrap()
+            .earned
+    }
+
+    #[test]
+    fn deposit() {
+        let mut storage = MockStorage::new();
+        let alice = Addr::unchecked("alice");
+        setup(&mut storage, 2);
+
+        let fake = Cw20CoinVerified {
+            address: Addr::unchecked("fake"),
+            amount: Uint128::new(100),
+        };
+        assert!(matches!(
+            BOARDROOM.deposit(&mut storage, &alice, fake),
+            Err(ContractError::InvalidDepositToken { .. })
+        ));
+        assert!(matches!(
+            BOARDROOM.deposit(&mut storage, &alice, share(0)),
+            Err(ContractError::EmptyAmount {})
+        ));
+
+        BOARDROOM.deposit(&mut storage, &alice, share(100)).unwrap();
+        BOARDROOM.deposit(&mut storage, &alice, share(50)).unwrap();
+        let member = BOARDROOM.query_member(&storage, &alice).unwrap();
+        assert_eq!(member.balance, Uint128::new(150));
+        assert_eq!(member.unlock_epoch, 2);
+        assert_eq!(
+            BOARDROOM.get_info(&storage).unwrap().total_staked,
+            Uint128::new(150)
+        );
+    }
+
+    #[test]
+    fn withdraw_after_lockup() {
+        let mut storage = MockStorage::new();
+        let alice = Addr::unchecked("alice");
+        setup(&mut storage, 2);
+        BOARDROOM.deposit(&mut storage, &alice, share(100)).unwrap();
+
+        allocate(&mut storage, 1, 0);
+        assert!(matches!(
+            BOARDROOM.withdraw(&mut storage, &alice, Uint128::new(100)),
+            Err(ContractError::WithdrawLocked { epoch: 2 })
+        ));
+
+        allocate(&mut storage, 2, 0);
+        assert!(matches!(
+            BOARDROOM.withdraw(&mut storage, &alice, Uint128::new(101)),
+            Err(ContractError::WithdrawTooMuch {})
+        ));
+        let res = BOARDROOM
+            .withdraw(&mut storage, &alice, Uint128::new(40))
+            .unwrap();
+        assert_eq!(res.messages.len(), 1);
+
+        // a new deposit restarts the lockup
+        BOARDROOM.deposit(&mut storage, &alice, share(10)).unwrap();
+        assert!(matches!(
+            BOARDROOM.withdraw(&mut storage, &alice, Uint128::new(10)),
+            Err(ContractError::WithdrawLocked { epoch: 4 })
+        ));
+    }
+
+    #[test]
+    fn allocate_seigniorage() {
+        let mut storage = MockStorage::new();
+        setup(&mut storage, 0);
+
+        assert!(matches!(
+            BOARDROOM.allocate_seigniorage(
+                &mut storage,
+                &Addr::unchecked("alice"),
+                1,
+                Uint128::new(100),
+                100,
+            ),
+            Err(ContractError::Unauthorized {})
+        ));
+
+        // nothing staked yet, carried to the next allocation
+        allocate(&mut storage, 3, 100);
+        let info = BOARDROOM.get_info(&storage).unwrap();
+        assert_eq!(info.undistributed, Uint128::new(100));
+        assert_eq!((info.epoch, info.pool_epoch), (1, 3));
+
+        assert!(matches!(
+            BOARDROOM.allocate_seigniorage(
+                &mut storage,
+                &Addr::unchecked("pool"),
+                3,
+                Uint128::new(100),
+                300,
+            ),
+            Err(ContractError::EpochAlreadyAllocated { epoch: 3 })
+        ));
+
+        BOARDROOM
+            .deposit(&mut storage, &Addr::unchecked("alice"), share(100))
+            .unwrap();
+        allocate(&mut storage, 4, 50);
+        assert_eq!(earned(&storage, "alice"), Uint128::new(150));
+
+        // a new pool starts over from its first epoch
+        BOARDROOM
+            .set_pool(&mut storage, Addr::unchecked("new_pool"))
+            .unwrap();
+        BOARDROOM
+            .allocate_seigniorage(
+                &mut storage,
+                &Addr::unchecked("new_pool"),
+                1,
+                Uint128::new(10),
+                500,
+            )
+            .unwrap();
+        let info = BOARDROOM.get_info(&storage).unwrap();
+        assert_eq!((info.epoch, info.pool_epoch), (3, 1));
+        assert_eq!(info.total_allocated, Uint128::new(160));
+
+        let snapshots = BOARDROOM.get_snapshots(&storage, None, None).unwrap();
+        assert_eq!(
+            snapshots
+                .iter()
+                .map(|x| (x.epoch, x.pool_epoch))
+                .collect::<Vec<_>>(),
+            vec![(1, 3), (2, 4), (3, 1)]
+        );
+    }
+
+    #[test]
+    fn claim_reward_pro_rata() {
+        let mut storage = MockStorage::new();
+        let alice = Addr::unchecked("alice");
+        let bob = Addr::unchecked("bob");
+        setup(&mut storage, 0);
+
+        BOARDROOM.deposit(&mut storage, &alice, share(300)).unwrap();
+        BOARDROOM.deposit(&mut storage, &bob, share(100)).unwrap();
+        allocate(&mut storage, 1, 1_000);
+        // deposits after the allocation do not share it
+        BOARDROOM
+            .deposit(&mut storage, &Addr::unchecked("carol"), share(400))
+            .unwrap();
+        assert_eq!(earned(&storage, "carol"), Uint128::zero());
+
+        let res = BOARDROOM.claim_reward(&mut storage, &alice).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(earned(&storage, "alice"), Uint128::zero());
+        assert_eq!(earned(&storage, "bob"), Uint128::new(250));
+        assert!(matches!(
+            BOARDROOM.claim_reward(&mut storage, &alice),
+            Err(ContractError::NothingToClaim {})
+        ));
+
+        allocate(&mut storage, 2, 800);
+        assert_eq!(earned(&storage, "alice"), Uint128::new(300));
+        assert_eq!(earned(&storage, "bob"), Uint128::new(350));
+        assert_eq!(earned(&storage, "carol"), Uint128::new(400));
+    }
+}
@@ -0,0 +1,171 @@
+//! share holders deposit their share to earn the synth seigniorage minted by
+//! the pool at each expansion epoch. Seigniorage is split pro-rata to the
+//! stake at the epoch snapshot
+use crate::boardroom::BOARDROOM;
+use crate::error::ContractError;
+use crate::msg::{
+    Cw20ReceiveCallbackMsg, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, SnapshotsResponse,
+};
+
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    from_binary, to_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
+};
+use cw2::set_contract_version;
+use cw20::{Cw20CoinVerified, Cw20ReceiveMsg};
+use ownable::OWNABLE;
+
+// version info for migration info
+const CONTRACT_NAME: &str = "crates.io:fantastic-boardroom";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    let share_token = deps.api.addr_validate(msg.share_token.as_str())?;
+    let synth_token = deps.api.addr_validate(msg.synth_token.as_str())?;
+    let pool = deps.api.addr_validate(msg.pool.as_str())?;
+    OWNABLE.initialize(deps.storage, info.sender)?;
+    BOARDROOM.initialize(
+        deps.storage,
+        share_token,
+        synth_token,
+        pool,
+        msg.withdraw_lockup_epochs,
+    )?;
+
+    Ok(Response::new().add_attribute("method", "instantiate"))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(_deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
+    Ok(Response::default())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        ExecuteMsg::Receive(cw20_msg) => execute_receive_cw20(deps, info.sender, cw20_msg),
+        ExecuteMsg::Withdraw { amount } => BOARDROOM.withdraw(deps.storage, &info.sender, amount),
+        ExecuteMsg::ClaimReward {} => BOARDROOM.claim_reward(deps.storage, &info.sender),
+        ExecuteMsg::AllocateSeigniorage { epoch, amount } => BOARDROOM.allocate_seigniorage(
+            deps.storage,
+            &info.sender,
+            epoch,
+            amount,
+            env.block.time.seconds(),
+        ),
+        ExecuteMsg::SetWithdrawLockup {
+            withdraw_lockup_epochs,
+        } => {
+            OWNABLE.assert_owner(deps.storage, &info.sender)?;
+            BOARDROOM.set_withdraw_lockup(deps.storage, withdraw_lockup_epochs)
+        }
+        ExecuteMsg::SetPool { pool } => {
+            OWNABLE.assert_owner(deps.storage, &info.sender)?;
+            let pool = deps.api.addr_validate(pool.as_str())?;
+            BOARDROOM.set_pool(deps.storage, pool)
+        }
+        ExecuteMsg::TransferOwnership { new_owner } => OWNABLE
+            .execute_transfer_ownership(deps.storage, info, new_owner)
+            .map_err(ContractError::Ownable),
+        ExecuteMsg::AcceptOwnership {} => OWNABLE
+            .execute_accept_ownership(deps.storage, info)
+            .map_err(ContractError::Ownable),
+    }
+}
+
+fn execute_receive_cw20(
+    deps: DepsMut,
+    token_addr: Addr,
+    cw20_receive_msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let token_sender = deps.api.addr_validate(&cw20_receive_msg.sender)?;
+    let coin = Cw20CoinVerified {
+        address: token_addr,
+        amount: cw20_receive_msg.amount,
+    };
+    match from_binary(&cw20_receive_msg.msg) {
+        Ok(Cw20ReceiveCallbackMsg::Deposit {}) => {
+            BOARDROOM.deposit(deps.storage, &token_sender, coin)
+        }
+        Err(e) => Err(ContractError::Std(e)),
+    }
+}
+
+// ====== READ FUNCTIONS ======
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::BoardroomInfo {} => to_binary(&BOARDROOM.get_info(deps.storage)?),
+        QueryMsg::Member { address } => to_binary(&BOARDROOM.query_member(deps.storage, &address)?),
+        QueryMsg::Snapshots { start_after, limit } => to_binary(&SnapshotsResponse {
+            snapshots: BOARDROOM.get_snapshots(deps.storage, start_after, limit)?,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+
+    fn instantiate_msg() -> InstantiateMsg {
+        InstantiateMsg {
+            share_token: Addr::unchecked("share"),
+            synth_token: Addr::unchecked("synth"),
+            pool: Addr::unchecked("pool"),
+            withdraw_lockup_epochs: 2,
+        }
+    }
+
+    #[test]
+    fn instantiate_validates_addresses() {
+        let mut deps = mock_dependencies(&[]);
+        let err = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            InstantiateMsg {
+                pool: Addr::unchecked("Pool"),
+                ..instantiate_msg()
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Std(_)));
+
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            instantiate_msg(),
+        )
+        .unwrap();
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::SetPool {
+                pool: Addr::unchecked("Pool"),
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Std(_)));
+        assert_eq!(
+            BOARDROOM.get_info(&deps.storage).unwrap().pool,
+            Addr::unchecked("pool")
+        );
+    }
+}
@@ -0,0 +1,32 @@
+use cosmwasm_std::{Addr, StdError};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("Ownable: {0}")]
+    Ownable(#[from] ownable::Error),
+
+    #[error("Unauthorized")]
+    Unauthorized {},
+
+    #[error("Invalid deposit token, want {want} but {sent} sent")]
+    InvalidDepositToken { want: Addr, sent: Addr },
+
+    #[error("Cannot deposit or withdraw zero amount")]
+    EmptyAmount {},
+
+    #[error("Withdrawal amount larger than deposited")]
+    WithdrawTooMuch {},
+
+    #[error("Deposit is locked until epoch {epoch}")]
+    WithdrawLocked { epoch: u64 },
+
+    #[error("Epoch {epoch} is already allocated")]
+    EpochAlreadyAllocated { epoch: u64 },
+
+    #[error("Nothing to claim")]
+    NothingToClaim {},
+}
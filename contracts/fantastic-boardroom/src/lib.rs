@@ -0,0 +1,7 @@
+mod boardroom;
+pub mod contract;
+mod error;
+pub mod msg;
+pub mod state;
+
+pub use crate::error::ContractError;
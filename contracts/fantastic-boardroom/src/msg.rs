@@ -0,0 +1,77 @@
+use cosmwasm_std::{Addr, Uint128};
+use cw20::Cw20ReceiveMsg;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::state::Snapshot;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InstantiateMsg {
+    pub share_token: Addr,
+    pub synth_token: Addr,
+    pub pool: Addr,
+    pub withdraw_lockup_epochs: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecuteMsg {
+    Receive(Cw20ReceiveMsg),
+    /// withdraw deposited share once the lockup has passed
+    Withdraw {
+        amount: Uint128,
+    },
+    ClaimReward {},
+    /// called by the pool after minting `amount` synth to the boardroom at pool `epoch`
+    AllocateSeigniorage {
+        epoch: u64,
+        amount: Uint128,
+    },
+    SetWithdrawLockup {
+        withdraw_lockup_epochs: u64,
+    },
+    SetPool {
+        pool: Addr,
+    },
+    TransferOwnership {
+        new_owner: Addr,
+    },
+    AcceptOwnership {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Cw20ReceiveCallbackMsg {
+    Deposit {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    BoardroomInfo {},
+    Member {
+        address: Addr,
+    },
+    /// epoch snapshots, oldest first
+    Snapshots {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MemberResponse {
+    pub balance: Uint128,
+    pub earned: Uint128,
+    pub epoch_timer_start: u64,
+    /// first epoch the deposit can be withdrawn
+    pub unlock_epoch: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SnapshotsResponse {
+    pub snapshots: Vec<Snapshot>,
+}
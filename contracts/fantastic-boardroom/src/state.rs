@@ -0,0 +1,107 @@
+use cosmwasm_std::{Addr, Uint128};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+pub const REWARD_PER_SHARE_PRECISION: Uint128 = Uint128::new(10u128.pow(12));
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BoardroomInfo {
+    pub share_token: Addr,
+    pub synth_token: Addr,
+    /// fantastic-pool allowed to allocate seigniorage
+    pub pool: Addr,
+    /// epochs a deposit is locked before it can be withdrawn
+    pub withdraw_lockup_epochs: u64,
+    /// allocations received, lockups are counted in these epochs
+    pub epoch: u64,
+    /// pool epoch of the last allocation, reset when the pool changes
+    pub pool_epoch: u64,
+    pub total_staked: Uint128,
+    /// accumulated synth per share, in `REWARD_PER_SHARE_PRECISION`
+    pub reward_per_share: Uint128,
+    /// seigniorage received while nothing was staked, carried to the next epoch
+    pub undistributed: Uint128,
+    pub total_allocated: Uint128,
+}
+
+/// state of the boardroom right after an epoch allocation
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Snapshot {
+    pub epoch: u64,
+    pub pool_epoch: u64,
+    pub timestamp: u64,
+    pub reward_received: Uint128,
+    pub reward_per_share: Uint128,
+    pub total_staked: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct Member {
+    pub balance: Uint128,
+    /// `reward_per_share` at the last reward update
+    pub reward_per_share_paid: Uint128,
+    /// synth earned and not yet claimed
+    pub reward_earned: Uint128,
+    /// epoch of the last deposit, the lockup starts from here
+    pub epoch_timer_start: u64,
+}
+
+impl BoardroomInfo {
+    pub fn earned(&self, member: &Member) -> Uint128 {
+        member.balance.multiply_ratio(
+            self.reward_per_share - member.reward_per_share_paid,
+            REWARD_PER_SHARE_PRECISION,
+        ) + member.reward_earned
+    }
+
+    pub fn update_reward(&self, member: &mut Member) {
+        member.reward_earned = self.earned(member);
+        member.reward_per_share_paid = self.reward_per_share;
+    }
+
+    pub fn unlock_epoch(&self, member: &Member) -> u64 {
+        member.epoch_timer_start + self.withdraw_lockup_epochs
+    }
+
+    pub fn deposit(&mut self, member: &mut Member, amount: Uint128) {
+        self.update_reward(member);
+        member.balance += amount;
+        member.epoch_timer_start = self.epoch;
+        self.total_staked += amount;
+    }
+
+    pub fn withdraw(&mut self, member: &mut Member, amount: Uint128) {
+        self.update_reward(member);
+        member.balance -= amount;
+        self.total_staked -= amount;
+    }
+
+    pub fn claim(&self, member: &mut Member) -> Uint128 {
+        self.update_reward(member);
+        std::mem::take(&mut member.reward_earned)
+    }
+
+    /// distribute `amount` pro-rata to the current stake and start a new epoch
+    pub fn allocate(&mut self, pool_epoch: u64, amount: Uint128, now: u64) -> Snapshot {
+        let reward = amount + self.undistributed;
+        if self.total_staked.is_zero() {
+            self.undistributed = reward;
+        } else {
+            self.reward_per_share +=
+                reward.multiply_ratio(REWARD_PER_SHARE_PRECISION, self.total_staked);
+            self.undistributed = Uint128::zero();
+        }
+        self.epoch += 1;
+        self.pool_epoch = pool_epoch;
+        self.total_allocated += amount;
+
+        Snapshot {
+            epoch: self.epoch,
+            pool_epoch,
+            timestamp: now,
+            reward_received: amount,
+            reward_per_share: self.reward_per_share,
+            total_staked: self.total_staked,
+        }
+    }
+}
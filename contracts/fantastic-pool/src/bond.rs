@@ -0,0 +1,379 @@
+use cosmwasm_std::{to_binary, Addr, Response, StdResult, Storage, Uint128, WasmMsg};
+use cw20::{Cw20CoinVerified, Cw20ExecuteMsg};
+use cw_storage_plus::{Item, Map};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::epoch::EPOCH;
+use crate::helpers::Unit;
+use crate::pool::PoolConfig;
+use crate::ContractError;
+
+/// bonds are bought with synth below peg and redeemed 1:1 for synth out of
+/// later expansions
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct BondState {
+    /// bonds not yet redeemed
+    pub total_outstanding: Uint128,
+    pub total_issued: Uint128,
+    pub total_redeemed: Uint128,
+    /// synth burnt for bonds
+    pub total_burnt: Uint128,
+}
+
+pub struct Bond<'a> {
+    state: Item<'a, BondState>,
+    claims: Map<'a, &'a Addr, Uint128>,
+}
+
+impl<'a> Bond<'a> {
+    pub const fn new() -> Self {
+        Self {
+            state: Item::new("bond"),
+            claims: Map::new("bond_claims"),
+        }
+    }
+
+    pub fn initialize(&self, storage: &mut dyn Storage) -> StdResult<()> {
+        self.state.save(storage, &BondState::default())
+    }
+
+    /// bond totals, zero on a contract deployed before bonds existed
+    pub fn get_state(&self, storage: &dyn Storage) -> StdResult<BondState> {
+        self.state.may_load(storage).map(|x| x.unwrap_or_default())
+    }
+
+    pub fn get_claim(&self, storage: &dyn Storage, address: &Addr) -> StdResult<Uint128> {
+        self.claims
+            .may_load(storage, address)
+            .map(|x| x.unwrap_or_default())
+    }
+
+    /// burn the sent synth for bonds priced at the last epoch TWAP
+    pub fn purchase(
+        &self,
+        storage: &mut dyn Storage,
+        pool: &PoolConfig,
+        sender: &Addr,
+        synth_input: Cw20CoinVerified,
+        min_bond_out: Uint128,
+    ) -> Result<Response, ContractError> {
+        // important! user can send fake token to trigger this
+        if pool.synth != synth_input.address {
+            return Err(ContractError::BondInvalidSynthInput {
+                want: pool.synth.clone(),
+                send: synth_input.address,
+            });
+        }
+        if synth_input.amount.is_zero() {
+            return Err(ContractError::BondEmptyAmount {});
+        }
+
+        let bond_price = EPOCH.get_bond_price(storage)?;
+        let bond_out = synth_input
+            .amount
+            .multiply_ratio(Unit::precision(), bond_price);
+        if bond_out < min_bond_out {
+            return Err(ContractError::SlippageReached {});
+        }
+
+        let mut state = self.get_state(storage)?;
+        state.total_outstanding += bond_out;
+        state.total_issued += bond_out;
+        state.total_burnt += synth_input.amount;
+        self.state.save(storage, &state)?;
+        let claim = self.get_claim(storage, sender)? + bond_out;
+        self.claims.save(storage, sender, &claim)?;
+
+        let msg = WasmMsg::Execute {
+            contract_addr: pool.synth.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Burn {
+                amount: synth_input.amount,
+            })?,
+            funds: vec![],
+        };
+
+        Ok(Response::new()
+            .add_attribute("action", "buy_bond")
+            .add_attribute("input", synth_input.amount)
+            .add_attribute("bond_price", bond_price)
+            .add_attribute("bond_out", bond_out)
+            .add_message(msg))
+    }
+
+    /// mint synth for `amount` of bonds, limited by the bond allowance of
+    /// the current expansion epoch
+    pub fn redeem(
+        &self,
+        storage: &mut dyn Storage,
+        pool: &PoolConfig,
+        sender: &Addr,
+        amount: Uint128,
+    ) -> Result<Response, ContractError> {
+        if amount.is_zero() {
+            return Err(ContractError::BondEmptyAmount {});
+        }
+        let claim = self.get_claim(storage, sender)?;
+        if amount > claim {
+            return Err(ContractError::InsufficientBondClaim { claim });
+        }
+
+        EPOCH.use_bond_allowance(storage, amount)?;
+
+        let mut state = self.get_state(storage)?;
+        state.total_outstanding -= amount;
+        state.total_redeemed += amount;
+        self.state.save(storage, &state)?;
+        self.claims.save(storage, sender, &(claim - amount))?;
+
+        let msg = WasmMsg::Execute {
+            contract_addr: pool.synth.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Mint {
+                recipient: sender.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        };
+
+        Ok(Response::new()
+            .add_attribute("action", "redeem_bond")
+            .add_attribute("amount", amount)
+            .add_message(msg))
+    }
+}
+
+pub const BONDS: Bond = Bond::new();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epoch::Error as EpochError;
+    use crate::oracle::{OracleSource, PriceSource};
+    use cosmwasm_std::testing::{mock_env, MockStorage};
+    use cosmwasm_std::{
+        from_slice, to_binary, ContractResult, Empty, Querier, QuerierResult, QuerierWrapper,
+        QueryRequest, SystemResult, Timestamp, WasmQuery,
+    };
+    use cw20::TokenInfoResponse;
+
+    const EPOCH_DURATION: u64 = 3600;
+
+    /// answers the synth token info query with a fixed supply
+    struct SupplyQuerier(Uint128);
+
+    impl Querier for SupplyQuerier {
+        fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+            match from_slice(bin_request).unwrap() {
+                QueryRequest::<Empty>::Wasm(WasmQuery::Smart { .. }) => {
+                    SystemResult::Ok(ContractResult::Ok(
+                        to_binary(&TokenInfoResponse {
+                            name: "synth".to_string(),
+                            symbol: "SYN".to_string(),
+                            decimals: 6,
+                            total_supply: self.0,
+                        })
+                        .unwrap(),
+                    ))
+                }
+                _ => panic!("unexpected query"),
+            }
+        }
+    }
+
+    fn pool() -> PoolConfig {
+        let mut pool = PoolConfig::init("uusd".to_string(), Addr::unchecked("router"), vec![]);
+        pool.synth = Addr::unchecked("synth");
+        pool
+    }
+
+    fn synth(amount: u128) -> Cw20CoinVerified {
+        Cw20CoinVerified {
+            address: Addr::unchecked("synth"),
+            amount: Uint128::new(amount),
+        }
+    }
+
+    /// end an epoch at `now` with the synth TWAP fixed at `price`
+    fn run_epoch(storage: &mut dyn Storage, querier: &SupplyQuerier, price: u128, now: u64) {
+        let querier = QuerierWrapper::new(querier);
+        let sources = [OracleSource {
+            source: PriceSource::Fixed {
+                price: Uint128::new(price),
+            },
+            min_liquidity: Uint128::zero(),
+        }];
+        EPOCH
+            .config_oracle(storage, &querier, &sources, &[], now - EPOCH_DURATION)
+            .unwrap();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(now);
+        EPOCH.next_epoch(storage, &querier, env, &pool()).unwrap();
+    }
+
+    fn setup(storage: &mut dyn Storage) {
+        EPOCH.initialize(storage).unwrap();
+        EPOCH
+            .config_epoch(
+                storage,
+                EPOCH_DURATION,
+                Some(Unit::precision()),
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn purchase_below_peg() {
+        let mut storage = MockStorage::new();
+        let querier = SupplyQuerier(Unit::luna(1_000_000));
+        let alice = Addr::unchecked("alice");
+        setup(&mut storage);
+        run_epoch(&mut storage, &querier, 800_000, 10_000);
+
+        assert_eq!(
+            EPOCH.get_bond_price(&storage).unwrap(),
+            Uint128::new(800_000)
+        );
+        // 0.8 synth per bond
+        let res = BONDS
+            .purchase(
+                &mut storage,
+                &pool(),
+                &alice,
+                synth(80_000_000),
+                Uint128::zero(),
+            )
+            .unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(
+            BONDS.get_claim(&storage, &alice).unwrap(),
+            Uint128::new(100_000_000)
+        );
+
+        let state = BONDS.get_state(&storage).unwrap();
+        assert_eq!(state.total_outstanding, Uint128::new(100_000_000));
+        assert_eq!(state.total_burnt, Uint128::new(80_000_000));
+
+        // nothing to redeem out of a contraction
+        assert!(matches!(
+            BONDS.redeem(&mut storage, &pool(), &alice, Uint128::new(1)),
+            Err(ContractError::Epoch(EpochError::BondAllowanceExceeded {}))
+        ));
+    }
+
+    #[test]
+    fn purchase_slippage() {
+        let mut storage = MockStorage::new();
+        let querier = SupplyQuerier(Unit::luna(1_000_000));
+        setup(&mut storage);
+        run_epoch(&mut storage, &querier, 800_000, 10_000);
+
+        assert!(matches!(
+            BONDS.purchase(
+                &mut storage,
+                &pool(),
+                &Addr::unchecked("alice"),
+                synth(80_000_000),
+                Uint128::new(100_000_001),
+            ),
+            Err(ContractError::SlippageReached {})
+        ));
+        assert_eq!(BONDS.get_state(&storage).unwrap(), BondState::default());
+    }
+
+    #[test]
+    fn no_purchase_at_peg() {
+        let mut storage = MockStorage::new();
+        let querier = SupplyQuerier(Unit::luna(1_000_000));
+        setup(&mut storage);
+        run_epoch(&mut storage, &querier, 1_000_000, 10_000);
+
+        assert!(matches!(
+            BONDS.purchase(
+                &mut storage,
+                &pool(),
+                &Addr::unchecked("alice"),
+                synth(80_000_000),
+                Uint128::zero(),
+            ),
+            Err(ContractError::Epoch(EpochError::BondsNotAvailable {}))
+        ));
+    }
+
+    #[test]
+    fn redeem_limited_by_allowance() {
+        let mut storage = MockStorage::new();
+        let querier = SupplyQuerier(Unit::luna(1_000_000));
+        let alice = Addr::unchecked("alice");
+        setup(&mut storage);
+        run_epoch(&mut storage, &querier, 800_000, 10_000);
+        BONDS
+            .purchase(
+                &mut storage,
+                &pool(),
+                &alice,
+                synth(800_000_000),
+                Uint128::zero(),
+            )
+            .unwrap();
+
+        // 0.035% expansion of 1M synth, less than the 1000 bonds outstanding
+        run_epoch(&mut storage, &querier, 1_100_000, 20_000);
+        assert_eq!(
+            EPOCH.get_bond_allowance(&storage).unwrap(),
+            Uint128::new(350_000_000)
+        );
+
+        BONDS
+            .redeem(&mut storage, &pool(), &alice, Uint128::new(300_000_000))
+            .unwrap();
+        assert!(matches!(
+            BONDS.redeem(&mut storage, &pool(), &alice, Uint128::new(100_000_000)),
+            Err(ContractError::Epoch(EpochError::BondAllowanceExceeded {}))
+        ));
+        BONDS
+            .redeem(&mut storage, &pool(), &alice, Uint128::new(50_000_000))
+            .unwrap();
+
+        let state = BONDS.get_state(&storage).unwrap();
+        assert_eq!(state.total_outstanding, Uint128::new(650_000_000));
+        assert_eq!(state.total_redeemed, Uint128::new(350_000_000));
+        assert_eq!(EPOCH.get_bond_allowance(&storage).unwrap(), Uint128::zero());
+    }
+
+    #[test]
+    fn bond_allowance_has_mint_priority() {
+        let mut storage = MockStorage::new();
+        let querier = SupplyQuerier(Unit::luna(1_000_000));
+        setup(&mut storage);
+        run_epoch(&mut storage, &querier, 800_000, 10_000);
+        BONDS
+            .purchase(
+                &mut storage,
+                &pool(),
+                &Addr::unchecked("alice"),
+                synth(80_000_000),
+                Uint128::zero(),
+            )
+            .unwrap();
+        run_epoch(&mut storage, &querier, 1_100_000, 20_000);
+
+        // 350 synth expansion, 100 reserved for bonds
+        let now = 20_000 + EPOCH_DURATION / 2 + 1;
+        let wrapper = QuerierWrapper::new(&querier);
+        EPOCH
+            .assert_mint_amount(&storage, &wrapper, &pool(), Uint128::new(250_000_000), now)
+            .unwrap();
+        assert!(EPOCH
+            .assert_mint_amount(&storage, &wrapper, &pool(), Uint128::new(250_000_001), now)
+            .is_err());
+    }
+
+    #[test]
+    fn state_defaults_when_missing() {
+        let storage = MockStorage::new();
+        assert_eq!(BONDS.get_state(&storage).unwrap(), BondState::default());
+    }
+}
@@ -0,0 +1,282 @@
+use astroport::asset::{Asset, AssetInfo};
+use cosmwasm_std::{
+    to_binary, Addr, BankMsg, Coin, CosmosMsg, Order, QuerierWrapper, Response, StdError,
+    StdResult, Storage, Uint128, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+use cw_storage_plus::Map;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{helpers::Unit, oracle::PairOracle, ContractError};
+
+/// how a collateral is valued in the peg asset
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CollateralPrice {
+    /// the collateral is the peg asset itself
+    Peg,
+    /// TWAP of the oracle configured for this collateral, a pair priced
+    /// collateral uses an `AstroportXyk` oracle source
+    Oracle,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CollateralInfo {
+    pub asset: AssetInfo,
+    pub price: CollateralPrice,
+    /// max part of the total collateral value this asset can make up
+    pub weight_cap: Uint128,
+    /// disabled collateral cannot be minted with, but is still redeemable
+    pub enabled: bool,
+    /// intermediate assets to hop through when buying share, fallback to pool route
+    pub swap_route: Option<Vec<AssetInfo>>,
+
+    /// fee charged in this collateral
+    pub total_fee: Uint128,
+    pub total_unclaimed: Uint128,
+    /// keeper budget paid in this collateral
+    pub total_reserved: Uint128,
+}
+
+impl CollateralInfo {
+    pub fn new(
+        asset: AssetInfo,
+        price: CollateralPrice,
+        weight_cap: Uint128,
+        swap_route: Option<Vec<AssetInfo>>,
+    ) -> Self {
+        CollateralInfo {
+            asset,
+            price,
+            weight_cap,
+            enabled: true,
+            swap_route,
+            total_fee: Uint128::zero(),
+            total_unclaimed: Uint128::zero(),
+            total_reserved: Uint128::zero(),
+        }
+    }
+
+    /// oracle of the collateral, if priced by one
+    pub fn oracle(&self) -> Option<PairOracle> {
+        match &self.price {
+            CollateralPrice::Oracle => Some(PairOracle::collateral(&self.asset)),
+            _ => None,
+        }
+    }
+
+    /// value of one unit of collateral in peg asset, an oracle TWAP older than
+    /// `max_twap_age` is rejected
+    pub fn get_price(
+        &self,
+        storage: &dyn Storage,
+        now: u64,
+        max_twap_age: u64,
+    ) -> Result<Uint128, ContractError> {
+        match &self.price {
+            CollateralPrice::Peg => Ok(Unit::precision()),
+            CollateralPrice::Oracle => {
+                PairOracle::collateral(&self.asset).get_fresh_twap(storage, now, max_twap_age)
+            }
+        }
+    }
+
+    /// amount of collateral backing the pool, excluding fee, unclaimed redemption
+    /// and keeper budget
+    pub fn get_holding(&self, querier: &QuerierWrapper, this_addr: &Addr) -> StdResult<Uint128> {
+        let balance = self.asset.query_pool(querier, this_addr.clone())?;
+        Ok(balance
+            .saturating_sub(self.total_fee)
+            .saturating_sub(self.total_unclaimed)
+            .saturating_sub(self.total_reserved))
+    }
+
+    pub fn to_value(amount: Uint128, price: Uint128) -> Uint128 {
+        amount * price / Unit::precision()
+    }
+}
+
+pub struct Collaterals<'a>(Map<'a, &'a [u8], CollateralInfo>);
+
+impl<'a> Collaterals<'a> {
+    pub const fn new() -> Self {
+        Collaterals(Map::new("collateral"))
+    }
+
+    pub fn get(
+        &self,
+        storage: &dyn Storage,
+        asset: &AssetInfo,
+    ) -> Result<CollateralInfo, ContractError> {
+        self.0.may_load(storage, asset.as_bytes())?.ok_or_else(|| {
+            ContractError::CollateralNotRegistered {
+                asset: asset.to_string(),
+            }
+        })
+    }
+
+    /// load a collateral accepted for minting
+    pub fn get_enabled(
+        &self,
+        storage: &dyn Storage,
+        asset: &AssetInfo,
+    ) -> Result<CollateralInfo, ContractError> {
+        let collateral = self.get(storage, asset)?;
+        if !collateral.enabled {
+            return Err(ContractError::CollateralDisabled {
+                asset: asset.to_string(),
+            });
+        }
+        Ok(collateral)
+    }
+
+    pub fn save(&self, storage: &mut dyn Storage, collateral: &CollateralInfo) -> StdResult<()> {
+        self.0
+            .save(storage, collateral.asset.as_bytes(), collateral)
+    }
+
+    pub fn list(&self, storage: &dyn Storage) -> StdResult<Vec<CollateralInfo>> {
+        self.0
+            .range(storage, None, None, Order::Ascending)
+            .map(|item| item.map(|(_, collateral)| collateral))
+            .collect()
+    }
+
+    /// total value of all collateral backing the pool, in peg asset
+    pub fn total_value(
+        &self,
+        storage: &dyn Storage,
+        querier: &QuerierWrapper,
+        this_addr: &Addr,
+        now: u64,
+        max_twap_age: u64,
+    ) -> Result<Uint128, ContractError> {
+        let mut total = Uint128::zero();
+        for collateral in self.list(storage)? {
+            let holding = collateral.get_holding(querier, this_addr)?;
+            if !holding.is_zero() {
+                total += CollateralInfo::to_value(
+                    holding,
+                    collateral.get_price(storage, now, max_twap_age)?,
+                );
+            }
+        }
+        Ok(total)
+    }
+
+    /// make sure `asset` does not exceed its weight cap, `outflow` is the amount
+    /// about to leave the contract in the same transaction
+    #[allow(clippy::too_many_arguments)]
+    pub fn assert_weight_cap(
+        &self,
+        storage: &dyn Storage,
+        querier: &QuerierWrapper,
+        this_addr: &Addr,
+        asset: &AssetInfo,
+        outflow: Uint128,
+        now: u64,
+        max_twap_age: u64,
+    ) -> Result<(), ContractError> {
+        let mut total = Uint128::zero();
+        let mut asset_value = Uint128::zero();
+        let mut weight_cap = Unit::precision();
+        for collateral in self.list(storage)? {
+            let mut holding = collateral.get_holding(querier, this_addr)?;
+            let is_target = collateral.asset.equal(asset);
+            if is_target {
+                holding = holding.saturating_sub(outflow);
+                weight_cap = collateral.weight_cap;
+            }
+            if holding.is_zero() {
+                continue;
+            }
+            let value = CollateralInfo::to_value(
+                holding,
+                collateral.get_price(storage, now, max_twap_age)?,
+            );
+            total += value;
+            if is_target {
+                asset_value = value;
+            }
+        }
+
+        if asset_value * Unit::precision() > total * weight_cap {
+            return Err(ContractError::CollateralWeightExceeded {
+                asset: asset.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// register or update a collateral. Allow from admin only
+    pub fn config(
+        &self,
+        storage: &mut dyn Storage,
+        asset: AssetInfo,
+        price: CollateralPrice,
+        weight_cap: Uint128,
+        enabled: bool,
+        swap_route: Option<Vec<AssetInfo>>,
+    ) -> Result<Response, ContractError> {
+        if weight_cap > Unit::precision() {
+            return Err(ContractError::Std(StdError::generic_err(
+                "Weight cap cannot exceed 100%",
+            )));
+        }
+
+        let collateral = match self.0.may_load(storage, asset.as_bytes())? {
+            Some(mut collateral) => {
+                collateral.price = price;
+                collateral.weight_cap = weight_cap;
+                collateral.enabled = enabled;
+                collateral.swap_route = swap_route;
+                collateral
+            }
+            None => {
+                let mut collateral =
+                    CollateralInfo::new(asset.clone(), price, weight_cap, swap_route);
+                collateral.enabled = enabled;
+                collateral
+            }
+        };
+        self.save(storage, &collateral)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "config_collateral")
+            .add_attribute("asset", asset.to_string())
+            .add_attribute("weight_cap", weight_cap)
+            .add_attribute("enabled", enabled.to_string()))
+    }
+}
+
+/// add `amount` of `info` into a list of assets
+pub fn add_asset(assets: &mut Vec<Asset>, info: &AssetInfo, amount: Uint128) {
+    match assets.iter_mut().find(|x| x.info.equal(info)) {
+        Some(pivot) => pivot.amount += amount,
+        None => assets.push(Asset {
+            info: info.clone(),
+            amount,
+        }),
+    }
+}
+
+/// build the message sending `amount` of `info` to `recipient`
+pub fn transfer_asset(info: &AssetInfo, amount: Uint128, recipient: &Addr) -> StdResult<CosmosMsg> {
+    match info {
+        AssetInfo::NativeToken { denom } => Ok(CosmosMsg::Bank(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: vec![Coin::new(amount.u128(), denom)],
+        })),
+        AssetInfo::Token { contract_addr } => Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: contract_addr.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: recipient.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        })),
+    }
+}
+
+pub const COLLATERALS: Collaterals = Collaterals::new();
@@ -1,18 +1,28 @@
-use crate::epoch::EPOCH;
+use crate::bond::BONDS;
+use crate::collateral::{CollateralInfo, CollateralPrice, COLLATERALS};
+use crate::epoch::{ALLOCATE_SEIGNIORAGE_REPLY_ID, EPOCH};
 use crate::error::ContractError;
+use crate::fee::FEE;
+use crate::helpers::Unit;
+use crate::keeper::{KeeperAction, KEEPER};
 use crate::msg::{
-    CalcMintResult, CalcRedeemResult, Cw20CallbackMsg, ExecuteMsg, GetPriceResult, InstantiateMsg,
-    MigrateMsg, OracleInfoResponse, PoolInfoResponse, QueryMsg,
+    BondInfoResponse, CalcMintResult, CalcRedeemResult, CollateralInfoResponse,
+    CollateralPriceResult, CollateralRatioHistoryResponse, Cw20CallbackMsg, EpochHistoryResponse,
+    ExecuteMsg, GetPriceResult, InstantiateMsg, MigrateMsg, OracleInfoResponse, PokeResponse,
+    PokeStatus, PokeStep, PoolInfoResponse, QueryMsg, TwapResponse, UserBondResponse,
 };
-use crate::oracle::{SHARE_ORACLE, SYNTH_ORACLE};
-use crate::pool::{UserInfo, POOL};
+use crate::oracle::{
+    OracleSource, PairOracle, PairOracleState, PriceSource, SHARE_ORACLE, SYNTH_ORACLE,
+};
+use crate::pool::{PoolConfig, UserInfo, POOL};
+use astroport::asset::{Asset, AssetInfo};
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    from_binary, to_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response,
-    StdError, StdResult, SubMsg, Uint128, WasmMsg,
+    from_binary, to_binary, Addr, Binary, Coin, ContractResult, CosmosMsg, Deps, DepsMut, Env,
+    MessageInfo, Reply, Response, StdError, StdResult, SubMsg, Uint128, WasmMsg,
 };
-use cw2::set_contract_version;
+use cw2::{get_contract_version, set_contract_version};
 use cw20::{
     BalanceResponse, Cw20Coin, Cw20CoinVerified, Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg,
     MinterResponse,
@@ -42,11 +52,39 @@ pub fn instantiate(
             "Share max cap must be greater than zero",
         )));
     }
+    let router = deps.api.addr_validate(msg.router.as_str())?;
     OWNABLE.initialize(deps.storage, info.sender.clone())?;
-    POOL.initialize(deps.storage, msg.collateral_denom.clone())?;
+    POOL.initialize(
+        deps.storage,
+        msg.collateral_denom.clone(),
+        router,
+        msg.swap_route.clone().unwrap_or_default(),
+    )?;
+    let policy_events = POOL
+        .update_collateral_policy(
+            deps.storage,
+            msg.price_band,
+            msg.collateral_ratio_step,
+            msg.refresh_collateral_ratio_cooldown,
+        )?
+        .events;
     EPOCH.initialize(deps.storage)?;
     SYNTH_ORACLE.initialize(deps.storage)?;
     SHARE_ORACLE.initialize(deps.storage)?;
+    FEE.initialize(deps.storage)?;
+    KEEPER.initialize(deps.storage)?;
+    BONDS.initialize(deps.storage)?;
+    COLLATERALS.save(
+        deps.storage,
+        &CollateralInfo::new(
+            AssetInfo::NativeToken {
+                denom: msg.collateral_denom.clone(),
+            },
+            CollateralPrice::Peg,
+            Unit::precision(),
+            None,
+        ),
+    )?;
 
     let initial_synth_balances = info
         .funds
@@ -109,6 +147,7 @@ pub fn instantiate(
         .add_attribute("action", "instantiate")
         .add_attribute("owner", info.sender)
         .add_attribute("collateral_denom", msg.collateral_denom)
+        .add_events(policy_events)
         .add_submessages(messages))
 }
 
@@ -125,6 +164,15 @@ pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractE
             let contract_addr = deps.api.addr_validate(&res.contract_address)?;
             POOL.set_share_address(deps.storage, contract_addr)
         }
+        ALLOCATE_SEIGNIORAGE_REPLY_ID => {
+            let error = match msg.result {
+                ContractResult::Err(error) => error,
+                ContractResult::Ok(_) => String::new(),
+            };
+            Ok(Response::new()
+                .add_attribute("action", "allocate_seigniorage_failed")
+                .add_attribute("error", error))
+        }
         _ => Err(ContractError::Std(StdError::generic_err(
             "Invalid reply ID",
         ))),
@@ -132,7 +180,16 @@ pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractE
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(_deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
+    // the storage layout changed in 0.2.0 without a migration path, older
+    // contracts need a fresh deployment
+    let version = get_contract_version(deps.storage)?;
+    if version.contract != CONTRACT_NAME || version.version != CONTRACT_VERSION {
+        return Err(StdError::generic_err(format!(
+            "Cannot migrate from {} {}, deploy a new contract instead",
+            version.contract, version.version
+        )));
+    }
     Ok(Response::default())
 }
 
@@ -145,17 +202,77 @@ pub fn execute(
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::Mint { min_synth_out } => POOL.mint(
+        ExecuteMsg::Mint {
+            min_synth_out,
+            min_share_bought,
+        } => POOL.mint(
             deps.storage,
             &deps.querier,
             env,
             &info.sender,
-            info.funds,
+            native_collateral_input(&info.funds)?,
             min_synth_out,
+            min_share_bought,
+        ),
+        ExecuteMsg::Recollateralize { min_share_out } => POOL.recollateralize(
+            deps.storage,
+            &deps.querier,
+            env,
+            &info.sender,
+            native_collateral_input(&info.funds)?,
+            min_share_out,
         ),
         ExecuteMsg::Collect {} => POOL.collect(deps.storage, env, &info.sender),
-        ExecuteMsg::RefreshCollateralRatio {} => POOL.refresh_collateral_ratio(deps.storage, env),
-        ExecuteMsg::UpdateOracle {} => execute_update_oracle(deps, env.block.time.seconds()),
+        ExecuteMsg::RedeemBond { amount } => {
+            let pool = POOL.pool.load(deps.storage)?;
+            BONDS.redeem(deps.storage, &pool, &info.sender, amount)
+        }
+        ExecuteMsg::SetOracleMaxDeviation {
+            oracle,
+            max_deviation,
+        } => {
+            OWNABLE.assert_owner(deps.storage, &info.sender)?;
+            oracle
+                .oracle()
+                .set_max_deviation(deps.storage, max_deviation)?;
+            Ok(Response::new()
+                .add_attribute("action", "set_oracle_max_deviation")
+                .add_attribute("oracle", format!("{:?}", oracle))
+                .add_attribute(
+                    "max_deviation",
+                    max_deviation.map_or(String::from("none"), |x| x.to_string()),
+                ))
+        }
+        ExecuteMsg::SetOracleObservations {
+            oracle,
+            observation_interval,
+            max_observations,
+        } => {
+            OWNABLE.assert_owner(deps.storage, &info.sender)?;
+            oracle.oracle().set_observations(
+                deps.storage,
+                observation_interval,
+                max_observations,
+            )?;
+            Ok(Response::new()
+                .add_attribute("action", "set_oracle_observations")
+                .add_attribute("oracle", format!("{:?}", oracle))
+                .add_attribute("observation_interval", observation_interval.to_string())
+                .add_attribute("max_observations", max_observations.to_string()))
+        }
+        ExecuteMsg::RefreshCollateralRatio {} => {
+            let response = POOL.refresh_collateral_ratio(deps.storage, env)?;
+            let reward = KEEPER.reward(
+                deps.storage,
+                KeeperAction::RefreshCollateralRatio,
+                &info.sender,
+            )?;
+            Ok(response.add_messages(reward))
+        }
+        ExecuteMsg::UpdateOracle {} => {
+            execute_update_oracle(deps, info.sender, env.block.time.seconds())
+        }
+        ExecuteMsg::Poke {} => execute_poke(deps, env, info.sender),
         ExecuteMsg::SetFee {
             minting_fee,
             redemption_fee,
@@ -163,6 +280,37 @@ pub fn execute(
             OWNABLE.assert_owner(deps.storage, &info.sender)?;
             POOL.set_fee(deps.storage, minting_fee, redemption_fee)
         }
+        ExecuteMsg::SetFeeRecipients { recipients } => {
+            OWNABLE.assert_owner(deps.storage, &info.sender)?;
+            for recipient in &recipients {
+                deps.api.addr_validate(recipient.address.as_str())?;
+            }
+            FEE.set_recipients(deps.storage, recipients)
+        }
+        ExecuteMsg::DistributeFees {} => FEE.distribute(deps.storage),
+        ExecuteMsg::ConfigKeeper {
+            reward_asset,
+            update_oracle_reward,
+            update_epoch_reward,
+            refresh_collateral_ratio_reward,
+        } => {
+            OWNABLE.assert_owner(deps.storage, &info.sender)?;
+            reward_asset.check(deps.api)?;
+            let pool = POOL.pool.load(deps.storage)?;
+            KEEPER.config(
+                deps.storage,
+                &pool.share,
+                reward_asset,
+                update_oracle_reward,
+                update_epoch_reward,
+                refresh_collateral_ratio_reward,
+            )
+        }
+        ExecuteMsg::FundKeeper {} => {
+            OWNABLE.assert_owner(deps.storage, &info.sender)?;
+            let Asset { info, amount } = native_collateral_input(&info.funds)?;
+            KEEPER.fund(deps.storage, &info, amount)
+        }
         ExecuteMsg::TransferOwnership { new_owner } => OWNABLE
             .execute_transfer_ownership(deps.storage, info, new_owner)
             .map_err(|e| ContractError::Ownable(e)),
@@ -172,54 +320,181 @@ pub fn execute(
         ExecuteMsg::Toggle {
             mint_paused,
             redeem_paused,
+            recollateralize_paused,
+            buyback_paused,
         } => {
             OWNABLE.assert_owner(deps.storage, &info.sender)?;
-            POOL.toggle(deps.storage, mint_paused, redeem_paused)
+            POOL.toggle(
+                deps.storage,
+                mint_paused,
+                redeem_paused,
+                recollateralize_paused,
+                buyback_paused,
+            )
+        }
+        ExecuteMsg::SetSharePricing { mode, max_twap_age } => {
+            OWNABLE.assert_owner(deps.storage, &info.sender)?;
+            POOL.set_share_pricing(deps.storage, mode, max_twap_age)
+        }
+        ExecuteMsg::SetRecollateralizeConfig {
+            recollateralize_bonus,
+            buyback_fee,
+        } => {
+            OWNABLE.assert_owner(deps.storage, &info.sender)?;
+            POOL.set_recollateralize_config(deps.storage, recollateralize_bonus, buyback_fee)
         }
         ExecuteMsg::SetMinCollateralRatio { value } => {
             OWNABLE.assert_owner(deps.storage, &info.sender)?;
             POOL.set_min_collateral_ratio(deps.storage, value)
         }
+        ExecuteMsg::SetRatioController { controller } => {
+            OWNABLE.assert_owner(deps.storage, &info.sender)?;
+            POOL.set_ratio_controller(deps.storage, controller)
+        }
+        ExecuteMsg::UpdateCollateralPolicy {
+            price_band,
+            collateral_ratio_step,
+            refresh_collateral_ratio_cooldown,
+        } => {
+            OWNABLE.assert_owner(deps.storage, &info.sender)?;
+            POOL.update_collateral_policy(
+                deps.storage,
+                price_band,
+                collateral_ratio_step,
+                refresh_collateral_ratio_cooldown,
+            )
+        }
+        ExecuteMsg::SetRouter { router, swap_route } => {
+            OWNABLE.assert_owner(deps.storage, &info.sender)?;
+            let router = deps.api.addr_validate(router.as_str())?;
+            POOL.set_router(deps.storage, router, swap_route)
+        }
+        ExecuteMsg::SetBuybackSlippage { max_slippage_bps } => {
+            OWNABLE.assert_owner(deps.storage, &info.sender)?;
+            POOL.set_buyback_slippage(deps.storage, max_slippage_bps)
+        }
+        ExecuteMsg::ConfigCollateral {
+            asset,
+            price,
+            weight_cap,
+            enabled,
+            swap_route,
+        } => {
+            OWNABLE.assert_owner(deps.storage, &info.sender)?;
+            asset.check(deps.api)?;
+            COLLATERALS.config(deps.storage, asset, price, weight_cap, enabled, swap_route)
+        }
         ExecuteMsg::ConfigShareOracle {
-            pair_addr,
-            base_index,
+            sources,
+            quote_legs,
             twap_period,
         } => execute_config_share_oracle(
             deps,
             info.sender,
-            pair_addr,
-            base_index,
+            sources,
+            quote_legs.unwrap_or_default(),
             twap_period,
             env.block.time.seconds(),
         ),
         ExecuteMsg::ConfigSynthOracle {
-            pair_addr,
-            base_index,
+            sources,
+            quote_legs,
             twap_period,
         } => execute_config_synth_oracle(
             deps,
             info.sender,
-            pair_addr,
-            base_index,
+            sources,
+            quote_legs.unwrap_or_default(),
             twap_period,
             env.block.time.seconds(),
         ),
-        ExecuteMsg::UpdateEpoch {} => execute_update_epoch(deps, env),
+        ExecuteMsg::ConfigCollateralOracle {
+            asset,
+            sources,
+            quote_legs,
+            twap_period,
+        } => {
+            OWNABLE.assert_owner(deps.storage, &info.sender)?;
+            COLLATERALS.get(deps.storage, &asset)?;
+            let quote_legs = quote_legs.unwrap_or_default();
+            PairOracle::collateral(&asset).config(
+                deps.storage,
+                &deps.querier,
+                &sources,
+                &quote_legs,
+                twap_period,
+                env.block.time.seconds(),
+            )?;
+            Ok(Response::new()
+                .add_attribute("action", "config_collateral_oracle")
+                .add_attribute("asset", asset.to_string())
+                .add_attribute("sources", sources.len().to_string())
+                .add_attribute("quote_legs", quote_legs.len().to_string())
+                .add_attribute("twap_period", twap_period.to_string()))
+        }
+        ExecuteMsg::UpdateEpoch {} => execute_update_epoch(deps, env, info.sender),
         ExecuteMsg::BurnShare {} => execute_burn_share(deps, env),
+        ExecuteMsg::AllocateSeigniorage { epoch, amount } => {
+            if info.sender != env.contract.address {
+                return Err(ContractError::Unauthorized {});
+            }
+            let pool = POOL.pool.load(deps.storage)?;
+            EPOCH
+                .allocate_seigniorage(deps.storage, &pool.synth, epoch, amount)
+                .map_err(|e| e.into())
+        }
         ExecuteMsg::Receive(cw20_msg) => execute_receive(deps, env, info.sender, cw20_msg),
         ExecuteMsg::SetEpochConfig {
             ceil_price,
             epoch_duration,
             max_expansion_rate,
+            supply_tiers,
+            expansion_rates,
         } => {
             OWNABLE.assert_owner(deps.storage, &info.sender)?;
             EPOCH
-                .config_epoch(deps.storage, epoch_duration, ceil_price, max_expansion_rate)
+                .config_epoch(
+                    deps.storage,
+                    epoch_duration,
+                    ceil_price,
+                    max_expansion_rate,
+                    supply_tiers,
+                    expansion_rates,
+                )
+                .map_err(|e| e.into())
+        }
+        ExecuteMsg::SetSeigniorage { seigniorage } => {
+            OWNABLE.assert_owner(deps.storage, &info.sender)?;
+            if let Some(seigniorage) = &seigniorage {
+                seigniorage.check(deps.api)?;
+            }
+            let pool = POOL.pool.load(deps.storage)?;
+            EPOCH
+                .config_seigniorage(
+                    deps.storage,
+                    &deps.querier,
+                    &env.contract.address,
+                    &pool,
+                    seigniorage,
+                )
                 .map_err(|e| e.into())
         }
     }
 }
 
+/// only one collateral coin can be sent at a time
+fn native_collateral_input(funds: &[Coin]) -> Result<Asset, ContractError> {
+    match funds {
+        [coin] => Ok(Asset {
+            info: AssetInfo::NativeToken {
+                denom: coin.denom.clone(),
+            },
+            amount: coin.amount,
+        }),
+        _ => Err(ContractError::MintInvalidCollateralAmount {}),
+    }
+}
+
 fn execute_receive(
     deps: DepsMut,
     env: Env,
@@ -227,7 +502,68 @@ fn execute_receive(
     envelop: Cw20ReceiveMsg,
 ) -> Result<Response, ContractError> {
     match from_binary(&envelop.msg) {
+        Ok(Cw20CallbackMsg::Mint {
+            min_synth_out,
+            min_share_bought,
+        }) => {
+            let collateral_input = Asset {
+                info: AssetInfo::Token {
+                    contract_addr: token,
+                },
+                amount: envelop.amount,
+            };
+
+            let sender = deps.api.addr_validate(&envelop.sender)?;
+            POOL.mint(
+                deps.storage,
+                &deps.querier,
+                env,
+                &sender,
+                collateral_input,
+                min_synth_out,
+                min_share_bought,
+            )
+        }
+        Ok(Cw20CallbackMsg::Recollateralize { min_share_out }) => {
+            let collateral_input = Asset {
+                info: AssetInfo::Token {
+                    contract_addr: token,
+                },
+                amount: envelop.amount,
+            };
+
+            let sender = deps.api.addr_validate(&envelop.sender)?;
+            POOL.recollateralize(
+                deps.storage,
+                &deps.querier,
+                env,
+                &sender,
+                collateral_input,
+                min_share_out,
+            )
+        }
+        Ok(Cw20CallbackMsg::Buyback {
+            collateral,
+            min_collateral_out,
+        }) => {
+            let share_input = Cw20CoinVerified {
+                address: token,
+                amount: envelop.amount,
+            };
+
+            let sender = deps.api.addr_validate(&envelop.sender)?;
+            POOL.buyback(
+                deps.storage,
+                &deps.querier,
+                env,
+                &sender,
+                share_input,
+                collateral,
+                min_collateral_out,
+            )
+        }
         Ok(Cw20CallbackMsg::Redeem {
+            collateral,
             min_collateral_out,
             min_share_out,
         }) => {
@@ -243,10 +579,32 @@ fn execute_receive(
                 env,
                 &sender,
                 synth_input,
+                collateral,
                 min_collateral_out,
                 min_share_out,
             )
         }
+        Ok(Cw20CallbackMsg::FundKeeper {}) => {
+            let sender = deps.api.addr_validate(&envelop.sender)?;
+            OWNABLE.assert_owner(deps.storage, &sender)?;
+            KEEPER.fund(
+                deps.storage,
+                &AssetInfo::Token {
+                    contract_addr: token,
+                },
+                envelop.amount,
+            )
+        }
+        Ok(Cw20CallbackMsg::BuyBond { min_bond_out }) => {
+            let synth_input = Cw20CoinVerified {
+                address: token,
+                amount: envelop.amount,
+            };
+
+            let sender = deps.api.addr_validate(&envelop.sender)?;
+            let pool = POOL.pool.load(deps.storage)?;
+            BONDS.purchase(deps.storage, &pool, &sender, synth_input, min_bond_out)
+        }
         Err(err) => {
             return Err(ContractError::Std(err));
         }
@@ -255,14 +613,20 @@ fn execute_receive(
 
 pub fn execute_burn_share(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
     let pool = POOL.pool.load(deps.storage)?;
-    let BalanceResponse {
-        balance: burn_amount,
-    } = deps.querier.query_wasm_smart(
+    let BalanceResponse { balance } = deps.querier.query_wasm_smart(
         pool.share.to_string(),
         &Cw20QueryMsg::Balance {
             address: env.contract.address.to_string(),
         },
     )?;
+    // keeper budget paid in share is not burnt
+    let reserved = KEEPER.get_reserved(
+        deps.storage,
+        &AssetInfo::Token {
+            contract_addr: pool.share.clone(),
+        },
+    )?;
+    let burn_amount = balance.saturating_sub(reserved);
 
     let msg = WasmMsg::Execute {
         contract_addr: pool.share.to_string(),
@@ -278,24 +642,38 @@ pub fn execute_burn_share(deps: DepsMut, env: Env) -> Result<Response, ContractE
         .add_message(msg))
 }
 
-fn execute_update_oracle(deps: DepsMut, now: u64) -> Result<Response, ContractError> {
-    SHARE_ORACLE
-        .update_twap(deps.storage, &deps.querier, now)
-        .ok(); // ignore error
-    SYNTH_ORACLE
-        .update_twap(deps.storage, &deps.querier, now)
-        .ok();
+fn execute_update_oracle(deps: DepsMut, sender: Addr, now: u64) -> Result<Response, ContractError> {
+    let mut updated = false;
+    let mut oracles = vec![SHARE_ORACLE, SYNTH_ORACLE];
+    oracles.extend(
+        COLLATERALS
+            .list(deps.storage)?
+            .iter()
+            .filter_map(|x| x.oracle()),
+    );
+    for oracle in oracles {
+        // ignore error
+        updated |= oracle.update_twap(deps.storage, &deps.querier, now).is_ok();
+    }
+
+    // only pay for a call that moved at least one oracle
+    let reward = if updated {
+        KEEPER.reward(deps.storage, KeeperAction::UpdateOracle, &sender)?
+    } else {
+        None
+    };
 
     Ok(Response::new()
         .add_attribute("action", "update_oracle")
-        .add_attribute("timestamp", now.to_string()))
+        .add_attribute("timestamp", now.to_string())
+        .add_messages(reward))
 }
 
 pub fn execute_config_share_oracle(
     deps: DepsMut,
     sender: Addr,
-    pair_addr: Addr,
-    share_index: u8,
+    sources: Vec<OracleSource>,
+    quote_legs: Vec<PriceSource>,
     twap_period: u64,
     now: u64,
 ) -> Result<Response, ContractError> {
@@ -304,24 +682,24 @@ pub fn execute_config_share_oracle(
     SHARE_ORACLE.config(
         deps.storage,
         &deps.querier,
-        &pair_addr,
-        share_index,
+        &sources,
+        &quote_legs,
         twap_period,
         now,
     )?;
 
     Ok(Response::new()
         .add_attribute("action", "config_share_oracle")
-        .add_attribute("pair_address", pair_addr)
-        .add_attribute("base_index", share_index.to_string())
+        .add_attribute("sources", sources.len().to_string())
+        .add_attribute("quote_legs", quote_legs.len().to_string())
         .add_attribute("twap_period", twap_period.to_string()))
 }
 
 pub fn execute_config_synth_oracle(
     deps: DepsMut,
     sender: Addr,
-    pair_addr: Addr,
-    synth_index: u8,
+    sources: Vec<OracleSource>,
+    quote_legs: Vec<PriceSource>,
     twap_period: u64,
     now: u64,
 ) -> Result<Response, ContractError> {
@@ -330,18 +708,18 @@ pub fn execute_config_synth_oracle(
     SYNTH_ORACLE.config(
         deps.storage,
         &deps.querier,
-        &pair_addr,
-        synth_index,
+        &sources,
+        &quote_legs,
         twap_period,
         now,
     )?;
 
-    EPOCH.config_oracle(deps.storage, &deps.querier, &pair_addr, synth_index, now)?;
+    EPOCH.config_oracle(deps.storage, &deps.querier, &sources, &quote_legs, now)?;
 
     Ok(Response::new()
         .add_attribute("action", "config_synth_oracle")
-        .add_attribute("pair_address", pair_addr)
-        .add_attribute("base_index", synth_index.to_string())
+        .add_attribute("sources", sources.len().to_string())
+        .add_attribute("quote_legs", quote_legs.len().to_string())
         .add_attribute("twap_period", twap_period.to_string()))
 }
 
@@ -351,71 +729,204 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::GetPoolInfo {} => to_binary(&query_pool_info(deps, env)?),
         QueryMsg::GetUserInfo { address } => to_binary(&query_user_info(deps, address)?),
-        QueryMsg::CalcMint { collateral_amount } => {
-            to_binary(&query_calc_mint(deps, collateral_amount)?)
+        QueryMsg::AllUsers { start_after, limit } => {
+            to_binary(&POOL.get_users(deps.storage, start_after, limit, false)?)
+        }
+        QueryMsg::UsersWithUnclaimed { start_after, limit } => {
+            to_binary(&POOL.get_users(deps.storage, start_after, limit, true)?)
+        }
+        QueryMsg::CalcMint {
+            collateral_amount,
+            collateral,
+        } => to_binary(&query_calc_mint(deps, env, collateral_amount, collateral)?),
+        QueryMsg::CalcRedeem {
+            synth_amount,
+            collateral,
+        } => to_binary(&query_calc_redeem(deps, env, synth_amount, collateral)?),
+        QueryMsg::GetPrice {} => to_binary(&query_get_price(deps, env)?),
+        QueryMsg::GetCollateralValue {} => to_binary(&POOL.collateral_status(
+            deps.storage,
+            &deps.querier,
+            &env.contract.address,
+            env.block.time.seconds(),
+        )?),
+        QueryMsg::CheckInvariants {
+            start_after,
+            limit,
+            user_sums,
+        } => to_binary(&POOL.check_invariants(
+            deps.storage,
+            &deps.querier,
+            &env.contract.address,
+            start_after,
+            limit,
+            user_sums,
+        )?),
+        QueryMsg::GetFeeInfo {} => to_binary(&FEE.get_state(deps.storage)?),
+        QueryMsg::GetKeeperInfo {} => to_binary(&KEEPER.get_state(deps.storage)?),
+        QueryMsg::GetEpochConfig {} => to_binary(&EPOCH.get_config(deps.storage)?),
+        QueryMsg::GetEpochInfo {} => {
+            to_binary(&EPOCH.get_info(deps.storage, env.block.time.seconds())?)
+        }
+        QueryMsg::GetMintAllowance {} => {
+            let pool = POOL.pool.load(deps.storage)?;
+            to_binary(&EPOCH.get_mint_allowance(
+                deps.storage,
+                &deps.querier,
+                &pool,
+                env.block.time.seconds(),
+            )?)
+        }
+        QueryMsg::GetBondInfo {} => to_binary(&query_bond_info(deps)?),
+        QueryMsg::GetUserBond { address } => {
+            let address = deps.api.addr_validate(&address)?;
+            to_binary(&UserBondResponse {
+                bonds: BONDS.get_claim(deps.storage, &address)?,
+            })
+        }
+        QueryMsg::EpochHistory { start_after, limit } => to_binary(&EpochHistoryResponse {
+            records: EPOCH.get_history(deps.storage, start_after, limit)?,
+        }),
+        QueryMsg::GetTwap { oracle, window } => to_binary(&TwapResponse {
+            twap: oracle.oracle().get_window_twap(deps.storage, window)?,
+            window,
+        }),
+        QueryMsg::CollateralRatioHistory { start_after, limit } => {
+            to_binary(&CollateralRatioHistoryResponse {
+                records: POOL.get_ratio_history(deps.storage, start_after, limit)?,
+            })
         }
-        QueryMsg::CalcRedeem { synth_amount } => to_binary(&query_calc_redeem(deps, synth_amount)?),
-        QueryMsg::GetPrice {} => to_binary(&query_get_price(deps)?),
     }
 }
 
 fn query_pool_info(deps: Deps, env: Env) -> StdResult<PoolInfoResponse> {
     let pool = POOL.pool.load(deps.storage)?;
 
-    // query balance of native token (or coin)
-    let collateral_balance = deps
-        .querier
-        .query_balance(&env.contract.address, &pool.collateral_denom)?
-        .amount;
+    let mut collaterals: Vec<CollateralInfoResponse> = vec![];
+    let mut total_collateral_value = Some(Uint128::zero());
+    for collateral in COLLATERALS.list(deps.storage)? {
+        let holding = collateral.get_holding(&deps.querier, &env.contract.address)?;
+        let price = collateral
+            .get_price(deps.storage, env.block.time.seconds(), pool.max_twap_age)
+            .ok();
+        let oracle = match collateral.oracle() {
+            Some(oracle) => oracle.get_state(deps.storage).ok(),
+            None => None,
+        };
+        total_collateral_value = total_collateral_value
+            .zip(price)
+            .map(|(total, price)| total + CollateralInfo::to_value(holding, price));
+        collaterals.push(CollateralInfoResponse {
+            collateral,
+            holding,
+            price,
+            oracle,
+        });
+    }
     let synth_oracle = SYNTH_ORACLE.get_state(deps.storage)?;
     let share_oracle = SHARE_ORACLE.get_state(deps.storage)?;
 
     Ok(PoolInfoResponse {
         collateral_denom: pool.collateral_denom.clone(),
-        collateral_balance: collateral_balance - pool.total_fee - pool.total_unclaimed_collateral,
+        collaterals,
+        total_collateral_value,
         synth: pool.synth,
         share: pool.share,
+        router: pool.router,
+        swap_route: pool.swap_route,
+        buyback_max_slippage_bps: pool.buyback_max_slippage_bps,
         collateral_ratio: pool.collateral_ratio,
         last_refresh_collateral_ratio: pool.last_refresh_collateral_ratio,
         collateral_ratio_step: pool.collateral_ratio_step,
         refresh_collateral_ratio_cooldown: pool.refresh_collateral_ratio_cooldown,
+        ratio_controller: pool.ratio_controller,
+        pid_state: pool.pid_state,
         price_band: pool.price_band,
         minting_fee: pool.minting_fee,
         redemption_fee: pool.redemption_fee,
-        total_unclaimed_collateral: pool.total_unclaimed_collateral,
         total_unclaimed_synth: pool.total_unclaimed_synth,
         total_unclaimed_share: pool.total_unclaimed_share,
+        total_redeemed_synth: pool.total_redeemed_synth,
         oracle: OracleInfoResponse {
             share: share_oracle,
             synth: synth_oracle,
         },
         min_collateral_ratio: pool.min_collateral_ratio,
+        share_price_mode: pool.share_price_mode.clone(),
+        max_twap_age: pool.max_twap_age,
+        recollateralize_bonus: pool.recollateralize_bonus,
+        buyback_fee: pool.buyback_fee,
         mint_paused: pool.mint_paused,
         redeem_paused: pool.redeem_paused,
+        recollateralize_paused: pool.recollateralize_paused,
+        buyback_paused: pool.buyback_paused,
         owner: OWNABLE.query_owner(deps.storage)?,
     })
 }
 
 fn query_user_info(deps: Deps, address: Addr) -> StdResult<UserInfo> {
-    let state = POOL.user.load(deps.storage, &address)?;
-    Ok(state)
+    let state = POOL.user.may_load(deps.storage, &address)?;
+    Ok(state.unwrap_or_default())
+}
+
+fn query_bond_info(deps: Deps) -> StdResult<BondInfoResponse> {
+    let state = BONDS.get_state(deps.storage)?;
+    Ok(BondInfoResponse {
+        total_outstanding: state.total_outstanding,
+        total_issued: state.total_issued,
+        total_redeemed: state.total_redeemed,
+        total_burnt: state.total_burnt,
+        bond_price: EPOCH.get_bond_price(deps.storage).ok(),
+        redeemable: EPOCH.get_bond_allowance(deps.storage)?,
+    })
 }
 
-fn query_calc_mint(deps: Deps, collateral_amount: Uint128) -> StdResult<CalcMintResult> {
+fn query_calc_mint(
+    deps: Deps,
+    env: Env,
+    collateral_amount: Uint128,
+    collateral: Option<AssetInfo>,
+) -> StdResult<CalcMintResult> {
     let pool = POOL.pool.load(deps.storage)?;
-    Ok(pool.calc_mint(collateral_amount))
+    let collateral = load_collateral(deps, &pool, collateral)?;
+    let collateral_price =
+        collateral.get_price(deps.storage, env.block.time.seconds(), pool.max_twap_age)?;
+    Ok(pool.calc_mint(collateral_amount, collateral_price))
 }
 
-fn query_calc_redeem(deps: Deps, synth_amount: Uint128) -> StdResult<CalcRedeemResult> {
+fn query_calc_redeem(
+    deps: Deps,
+    env: Env,
+    synth_amount: Uint128,
+    collateral: Option<AssetInfo>,
+) -> StdResult<CalcRedeemResult> {
     let pool = POOL.pool.load(deps.storage)?;
-    let share_price = SHARE_ORACLE.get_spot_price(deps.storage, &deps.querier)?;
+    let collateral = load_collateral(deps, &pool, collateral)?;
+    let collateral_price =
+        collateral.get_price(deps.storage, env.block.time.seconds(), pool.max_twap_age)?;
+    let share_price =
+        pool.get_share_price(deps.storage, &deps.querier, true, env.block.time.seconds())?;
+
+    Ok(pool.calc_redeem(synth_amount, share_price, collateral_price))
+}
 
-    Ok(pool.calc_redeem(synth_amount, share_price))
+/// load the given collateral, or the primary one if not specified
+fn load_collateral(
+    deps: Deps,
+    pool: &PoolConfig,
+    collateral: Option<AssetInfo>,
+) -> StdResult<CollateralInfo> {
+    let asset = collateral.unwrap_or_else(|| AssetInfo::NativeToken {
+        denom: pool.collateral_denom.clone(),
+    });
+    let collateral = COLLATERALS.get(deps.storage, &asset)?;
+    Ok(collateral)
 }
 
-fn query_get_price(deps: Deps) -> StdResult<GetPriceResult> {
-    let synth_spot = SYNTH_ORACLE.get_spot_price(deps.storage, &deps.querier)?;
-    let share_spot = SHARE_ORACLE.get_spot_price(deps.storage, &deps.querier)?;
+fn query_get_price(deps: Deps, env: Env) -> StdResult<GetPriceResult> {
+    let now = env.block.time.seconds();
+    let synth_spot = SYNTH_ORACLE.get_spot_price(deps.storage, &deps.querier, now)?;
+    let share_spot = SHARE_ORACLE.get_spot_price(deps.storage, &deps.querier, now)?;
     let share_twap = SHARE_ORACLE
         .get_twap(deps.storage)
         .map(|(twap, _)| twap)
@@ -424,17 +935,474 @@ fn query_get_price(deps: Deps) -> StdResult<GetPriceResult> {
         .get_twap(deps.storage)
         .map(|(twap, _)| twap)
         .ok();
+    let collaterals = COLLATERALS
+        .list(deps.storage)?
+        .into_iter()
+        .filter_map(|collateral| {
+            let oracle = collateral.oracle()?;
+            Some(CollateralPriceResult {
+                spot: oracle.get_spot_price(deps.storage, &deps.querier, now).ok(),
+                twap: oracle.get_twap(deps.storage).map(|(twap, _)| twap).ok(),
+                asset: collateral.asset,
+            })
+        })
+        .collect();
     Ok(GetPriceResult {
         share_spot,
         synth_spot,
         share_twap,
         synth_twap,
+        share_deviation: share_twap
+            .filter(|x| !x.is_zero())
+            .map(|twap| PairOracleState::calc_deviation(share_spot, twap)),
+        synth_deviation: synth_twap
+            .filter(|x| !x.is_zero())
+            .map(|twap| PairOracleState::calc_deviation(synth_spot, twap)),
+        collaterals,
     })
 }
 
-fn execute_update_epoch(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+/// update oracles, collateral ratio and epoch when due, keepers are paid per
+/// step that ran
+fn execute_poke(deps: DepsMut, env: Env, sender: Addr) -> Result<Response, ContractError> {
+    let now = env.block.time.seconds();
+    let mut result = PokeResponse { steps: vec![] };
+    let mut messages: Vec<CosmosMsg> = vec![];
+
+    let mut oracle_updated = false;
+    let mut oracles = vec![
+        ("update_share_oracle".to_string(), SHARE_ORACLE),
+        ("update_synth_oracle".to_string(), SYNTH_ORACLE),
+    ];
+    for collateral in COLLATERALS.list(deps.storage)? {
+        if let Some(oracle) = collateral.oracle() {
+            oracles.push((
+                format!("update_collateral_oracle:{}", collateral.asset),
+                oracle,
+            ));
+        }
+    }
+    for (step, oracle) in oracles {
+        let updated = oracle.update_twap(deps.storage, &deps.querier, now);
+        oracle_updated |= result.record(&step, updated).is_some();
+    }
+    if oracle_updated {
+        messages.extend(KEEPER.reward(deps.storage, KeeperAction::UpdateOracle, &sender)?);
+    }
+
+    // keep what the steps emit, as if they were called on their own
+    let mut ran: Vec<Response> = vec![];
+    let refreshed = POOL.refresh_collateral_ratio(deps.storage, env.clone());
+    if let Some(response) = result.record("refresh_collateral_ratio", refreshed) {
+        ran.push(response);
+        messages.extend(KEEPER.reward(
+            deps.storage,
+            KeeperAction::RefreshCollateralRatio,
+            &sender,
+        )?);
+    }
+
+    let pool = POOL.pool.load(deps.storage)?;
+    let next_epoch = EPOCH.next_epoch(deps.storage, &deps.querier, env, &pool);
+    if let Some(response) = result.record("update_epoch", next_epoch) {
+        ran.push(response);
+        messages.extend(KEEPER.reward(deps.storage, KeeperAction::UpdateEpoch, &sender)?);
+    }
+
+    let mut response = Response::new().add_attribute("action", "poke");
+    for PokeStep { step, status } in &result.steps {
+        let status = match status {
+            PokeStatus::Ran => "ran",
+            PokeStatus::Skipped { .. } => "skipped",
+        };
+        response = response.add_attribute(step, status);
+    }
+    for step in ran {
+        response = response
+            .add_submessages(step.messages)
+            .add_attributes(step.attributes)
+            .add_events(step.events);
+    }
+
+    Ok(response
+        .add_messages(messages)
+        .set_data(to_binary(&result)?))
+}
+
+fn execute_update_epoch(deps: DepsMut, env: Env, sender: Addr) -> Result<Response, ContractError> {
     let pool = POOL.pool.load(deps.storage)?;
-    EPOCH
-        .next_epoch(deps.storage, &deps.querier, env, &pool)
-        .map_err(|e| e.into())
+    let response = EPOCH.next_epoch(deps.storage, &deps.querier, env, &pool)?;
+    let reward = KEEPER.reward(deps.storage, KeeperAction::UpdateEpoch, &sender)?;
+    Ok(response.add_messages(reward))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epoch::SeigniorageConfig;
+    use crate::fee::FeeRecipient;
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+    };
+    use cosmwasm_std::{coins, OwnedDeps, Timestamp};
+
+    const START: u64 = 1_000_000;
+    const COOLDOWN: u64 = 600;
+    const REFRESH_REWARD: u128 = 10;
+
+    fn env_at(now: u64) -> Env {
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(now);
+        env
+    }
+
+    fn exec(
+        deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>,
+        now: u64,
+        sender: &str,
+        msg: ExecuteMsg,
+    ) -> Result<Response, ContractError> {
+        execute(deps.as_mut(), env_at(now), mock_info(sender, &[]), msg)
+    }
+
+    /// pool with a synth oracle fixed above peg and a keeper funded in uusd
+    fn setup() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies(&[]);
+        instantiate(
+            deps.as_mut(),
+            env_at(START),
+            mock_info("owner", &[]),
+            InstantiateMsg {
+                collateral_denom: "uusd".to_string(),
+                token_code_id: 1,
+                synth_symbol: "SYN".to_string(),
+                synth_name: "synth".to_string(),
+                share_symbol: "SHR".to_string(),
+                share_name: "share".to_string(),
+                share_max_cap: Uint128::new(1_000_000),
+                router: Addr::unchecked("router"),
+                swap_route: None,
+                price_band: None,
+                collateral_ratio_step: None,
+                refresh_collateral_ratio_cooldown: Some(COOLDOWN),
+            },
+        )
+        .unwrap();
+        POOL.set_synth_address(deps.as_mut().storage, Addr::unchecked("synth"))
+            .unwrap();
+        POOL.set_share_address(deps.as_mut().storage, Addr::unchecked("share"))
+            .unwrap();
+
+        exec(
+            &mut deps,
+            START,
+            "owner",
+            ExecuteMsg::ConfigSynthOracle {
+                sources: vec![OracleSource {
+                    source: PriceSource::Fixed {
+                        price: Uint128::new(1_100_000),
+                    },
+                    min_liquidity: Uint128::zero(),
+                }],
+                quote_legs: None,
+                twap_period: 100,
+            },
+        )
+        .unwrap();
+        exec(
+            &mut deps,
+            START,
+            "owner",
+            ExecuteMsg::ConfigKeeper {
+                reward_asset: AssetInfo::NativeToken {
+                    denom: "uusd".to_string(),
+                },
+                update_oracle_reward: Uint128::zero(),
+                update_epoch_reward: Uint128::zero(),
+                refresh_collateral_ratio_reward: Uint128::new(REFRESH_REWARD),
+            },
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            env_at(START),
+            mock_info("owner", &coins(1_000, "uusd")),
+            ExecuteMsg::FundKeeper {},
+        )
+        .unwrap();
+        deps
+    }
+
+    /// take a synth oracle snapshot at `now`
+    fn update_synth_oracle(deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>, now: u64) {
+        let deps = deps.as_mut();
+        SYNTH_ORACLE
+            .update_twap(deps.storage, &deps.querier, now)
+            .unwrap();
+    }
+
+    fn keeper_budget(deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>) -> Uint128 {
+        KEEPER.get_state(&deps.storage).unwrap().budget
+    }
+
+    #[test]
+    fn refresh_collateral_ratio_respects_cooldown() {
+        let mut deps = setup();
+        let now = START + 100;
+        update_synth_oracle(&mut deps, now);
+
+        let res = exec(
+            &mut deps,
+            now,
+            "keeper",
+            ExecuteMsg::RefreshCollateralRatio {},
+        )
+        .unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(keeper_budget(&deps), Uint128::new(1_000 - REFRESH_REWARD));
+        let pool = POOL.pool.load(&deps.storage).unwrap();
+        assert_eq!(pool.last_refresh_collateral_ratio, now);
+        let collateral_ratio = pool.collateral_ratio;
+
+        // a second refresh in the cooldown fails and pays nothing
+        update_synth_oracle(&mut deps, now + 100);
+        let err = exec(
+            &mut deps,
+            now + 100,
+            "keeper",
+            ExecuteMsg::RefreshCollateralRatio {},
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::CollateralRatioRefreshCooldown));
+        assert_eq!(keeper_budget(&deps), Uint128::new(1_000 - REFRESH_REWARD));
+        assert_eq!(
+            POOL.pool.load(&deps.storage).unwrap().collateral_ratio,
+            collateral_ratio
+        );
+
+        exec(
+            &mut deps,
+            now + COOLDOWN,
+            "keeper",
+            ExecuteMsg::RefreshCollateralRatio {},
+        )
+        .unwrap();
+        assert_eq!(
+            keeper_budget(&deps),
+            Uint128::new(1_000 - 2 * REFRESH_REWARD)
+        );
+    }
+
+    #[test]
+    fn collateral_policy_cooldown_sets_next_refresh() {
+        let mut deps = setup();
+        let now = START + 100;
+        update_synth_oracle(&mut deps, now);
+        exec(
+            &mut deps,
+            now,
+            "keeper",
+            ExecuteMsg::RefreshCollateralRatio {},
+        )
+        .unwrap();
+
+        let set_cooldown = |cooldown: u64| ExecuteMsg::UpdateCollateralPolicy {
+            price_band: None,
+            collateral_ratio_step: None,
+            refresh_collateral_ratio_cooldown: Some(cooldown),
+        };
+
+        // a shorter cooldown allows an earlier refresh
+        exec(&mut deps, now, "owner", set_cooldown(200)).unwrap();
+        update_synth_oracle(&mut deps, now + 200);
+        exec(
+            &mut deps,
+            now + 200,
+            "keeper",
+            ExecuteMsg::RefreshCollateralRatio {},
+        )
+        .unwrap();
+
+        // a longer one pushes it back
+        exec(&mut deps, now + 200, "owner", set_cooldown(1_000)).unwrap();
+        update_synth_oracle(&mut deps, now + 200 + COOLDOWN);
+        let err = exec(
+            &mut deps,
+            now + 200 + COOLDOWN,
+            "keeper",
+            ExecuteMsg::RefreshCollateralRatio {},
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::CollateralRatioRefreshCooldown));
+        exec(
+            &mut deps,
+            now + 1_200,
+            "keeper",
+            ExecuteMsg::RefreshCollateralRatio {},
+        )
+        .unwrap();
+    }
+
+    fn poke_status(res: &Response, step: &str) -> String {
+        res.attributes
+            .iter()
+            .find(|x| x.key == step)
+            .map(|x| x.value.clone())
+            .unwrap()
+    }
+
+    #[test]
+    fn poke_skips_refresh_in_cooldown() {
+        let mut deps = setup();
+        let res = exec(&mut deps, START + 100, "keeper", ExecuteMsg::Poke {}).unwrap();
+        assert_eq!(poke_status(&res, "refresh_collateral_ratio"), "ran");
+        assert_eq!(keeper_budget(&deps), Uint128::new(1_000 - REFRESH_REWARD));
+
+        let res = exec(&mut deps, START + 200, "keeper", ExecuteMsg::Poke {}).unwrap();
+        assert_eq!(poke_status(&res, "update_synth_oracle"), "ran");
+        assert_eq!(poke_status(&res, "refresh_collateral_ratio"), "skipped");
+        assert_eq!(keeper_budget(&deps), Uint128::new(1_000 - REFRESH_REWARD));
+    }
+
+    #[test]
+    fn poke_skips_epoch_in_its_start_block() {
+        let mut deps = setup();
+        exec(
+            &mut deps,
+            START,
+            "owner",
+            ExecuteMsg::SetEpochConfig {
+                ceil_price: None,
+                epoch_duration: 0,
+                max_expansion_rate: None,
+                supply_tiers: None,
+                expansion_rates: None,
+            },
+        )
+        .unwrap();
+
+        let res = exec(&mut deps, START, "keeper", ExecuteMsg::Poke {}).unwrap();
+        assert_eq!(poke_status(&res, "update_epoch"), "skipped");
+        let err = exec(&mut deps, START, "keeper", ExecuteMsg::UpdateEpoch {}).unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::Epoch(crate::epoch::Error::EpochNotElapsed {})
+        ));
+    }
+
+    #[test]
+    fn set_seigniorage_rejects_bad_recipients() {
+        let mut deps = setup();
+        let config = SeigniorageConfig {
+            treasury: Addr::unchecked("treasury"),
+            treasury_rate: Uint128::new(200_000),
+            dev_fund: Addr::unchecked("dev_fund"),
+            dev_fund_rate: Uint128::new(100_000),
+            staking: Addr::unchecked("staking"),
+            staking_rate: Uint128::new(700_000),
+            boardroom: false,
+        };
+        let mut set = |seigniorage: SeigniorageConfig| {
+            exec(
+                &mut deps,
+                START,
+                "owner",
+                ExecuteMsg::SetSeigniorage {
+                    seigniorage: Some(seigniorage),
+                },
+            )
+        };
+
+        let err = set(SeigniorageConfig {
+            treasury: Addr::unchecked("Treasury"),
+            ..config.clone()
+        })
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Std(_)));
+
+        let err = set(SeigniorageConfig {
+            dev_fund: Addr::unchecked("treasury"),
+            ..config.clone()
+        })
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::Epoch(crate::epoch::Error::InvalidConfig { .. })
+        ));
+
+        let err = set(SeigniorageConfig {
+            dev_fund_rate: Uint128::zero(),
+            staking_rate: Uint128::new(800_000),
+            ..config.clone()
+        })
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::Epoch(crate::epoch::Error::InvalidConfig { .. })
+        ));
+
+        set(config).unwrap();
+    }
+
+    #[test]
+    fn allocate_seigniorage_is_internal() {
+        let mut deps = setup();
+        let err = exec(
+            &mut deps,
+            START,
+            "keeper",
+            ExecuteMsg::AllocateSeigniorage {
+                epoch: 1,
+                amount: Uint128::new(100),
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+    }
+
+    #[test]
+    fn rejected_allocation_reply_keeps_the_epoch() {
+        let mut deps = setup();
+        let res = reply(
+            deps.as_mut(),
+            env_at(START),
+            Reply {
+                id: ALLOCATE_SEIGNIORAGE_REPLY_ID,
+                result: ContractResult::Err("boardroom rejected".to_string()),
+            },
+        )
+        .unwrap();
+        assert!(res.messages.is_empty());
+        assert_eq!(
+            res.attributes,
+            vec![
+                ("action", "allocate_seigniorage_failed"),
+                ("error", "boardroom rejected"),
+            ]
+        );
+    }
+
+    #[test]
+    fn set_fee_recipients_validates_addresses() {
+        let mut deps = setup();
+        let recipients = vec![
+            FeeRecipient {
+                address: Addr::unchecked("treasury"),
+                weight: Uint128::new(500_000),
+            },
+            FeeRecipient {
+                address: Addr::unchecked("Dev"),
+                weight: Uint128::new(500_000),
+            },
+        ];
+        let err = exec(
+            &mut deps,
+            START,
+            "owner",
+            ExecuteMsg::SetFeeRecipients { recipients },
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Std(_)));
+        assert!(FEE.get_state(&deps.storage).unwrap().recipients.is_empty());
+    }
 }
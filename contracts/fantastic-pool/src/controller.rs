@@ -0,0 +1,244 @@
+use cosmwasm_std::Uint128;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+use crate::{
+    helpers::{int128, to_i128, to_uint128, Unit},
+    ContractError,
+};
+
+/// max PID gain, 10x in precision unit
+const MAX_GAIN: Uint128 = Uint128::new(10_000_000);
+/// max accumulated deviation, 10x the peg
+const MAX_INTEGRAL_LIMIT: Uint128 = Uint128::new(10_000_000);
+
+/// how the collateral ratio reacts to synth TWAP leaving the peg
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RatioController {
+    /// move by `collateral_ratio_step` when TWAP is out of `price_band`
+    #[default]
+    FixedStep,
+    /// move by proportional, integral and derivative terms of the peg deviation.
+    /// Gains are in precision unit
+    Pid {
+        kp: Uint128,
+        ki: Uint128,
+        kd: Uint128,
+        /// bound of the accumulated deviation, against integral windup
+        integral_limit: Uint128,
+    },
+}
+
+impl RatioController {
+    pub fn validate(&self) -> Result<(), ContractError> {
+        if let RatioController::Pid {
+            kp,
+            ki,
+            kd,
+            integral_limit,
+        } = self
+        {
+            if [kp, ki, kd].iter().any(|&&x| x > MAX_GAIN) {
+                return Err(ContractError::InvalidCollateralPolicy {
+                    msg: format!("PID gain cannot exceed {}", MAX_GAIN),
+                });
+            }
+            if *integral_limit > MAX_INTEGRAL_LIMIT {
+                return Err(ContractError::InvalidCollateralPolicy {
+                    msg: format!("PID integral limit cannot exceed {}", MAX_INTEGRAL_LIMIT),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// PID memory kept between refreshes
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct PidState {
+    #[serde(with = "int128")]
+    #[schemars(with = "String")]
+    pub integral: i128,
+    #[serde(with = "int128")]
+    #[schemars(with = "String")]
+    pub last_error: i128,
+}
+
+impl PidState {
+    /// signed change of collateral ratio for the given synth TWAP, positive
+    /// when synth trades above peg so the ratio should go down
+    pub fn step(
+        &mut self,
+        synth_twap: Uint128,
+        kp: Uint128,
+        ki: Uint128,
+        kd: Uint128,
+        integral_limit: Uint128,
+    ) -> i128 {
+        let precision = to_i128(Unit::precision());
+        let error = i128::try_from(synth_twap.u128())
+            .unwrap_or(i128::MAX)
+            .saturating_sub(precision);
+        let limit = to_i128(integral_limit);
+
+        self.integral = self.integral.saturating_add(error).clamp(-limit, limit);
+        let derivative = error.saturating_sub(self.last_error);
+        self.last_error = error;
+
+        to_i128(kp)
+            .saturating_mul(error)
+            .saturating_add(to_i128(ki).saturating_mul(self.integral))
+            .saturating_add(to_i128(kd).saturating_mul(derivative))
+            / precision
+    }
+}
+
+/// apply a signed step to `collateral_ratio`, bounded by min and max ratio
+pub fn apply_step(
+    collateral_ratio: Uint128,
+    step: i128,
+    min_collateral_ratio: Uint128,
+    max_collateral_ratio: Uint128,
+) -> Uint128 {
+    let next = to_i128(collateral_ratio)
+        .saturating_sub(step)
+        .clamp(to_i128(min_collateral_ratio), to_i128(max_collateral_ratio));
+    to_uint128(next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ONE: u128 = 1_000_000;
+
+    fn pid(kp: u128, ki: u128, kd: u128, integral_limit: u128) -> RatioController {
+        RatioController::Pid {
+            kp: Uint128::new(kp),
+            ki: Uint128::new(ki),
+            kd: Uint128::new(kd),
+            integral_limit: Uint128::new(integral_limit),
+        }
+    }
+
+    #[test]
+    fn validate_bounds_gains() {
+        assert!(RatioController::FixedStep.validate().is_ok());
+        assert!(pid(10 * ONE, 10 * ONE, 10 * ONE, 10 * ONE)
+            .validate()
+            .is_ok());
+        assert!(pid(10 * ONE + 1, 0, 0, 0).validate().is_err());
+        assert!(pid(0, 0, 10 * ONE + 1, 0).validate().is_err());
+        assert!(pid(0, 0, 0, 10 * ONE + 1).validate().is_err());
+    }
+
+    #[test]
+    fn step_proportional() {
+        let mut state = PidState::default();
+        // synth 5% above peg with kp = 0.5
+        let step = state.step(
+            Uint128::new(1_050_000),
+            Uint128::new(ONE / 2),
+            Uint128::zero(),
+            Uint128::zero(),
+            Uint128::new(ONE),
+        );
+        assert_eq!(step, 25_000);
+        assert_eq!(state.last_error, 50_000);
+
+        let step = state.step(
+            Uint128::new(950_000),
+            Uint128::new(ONE / 2),
+            Uint128::zero(),
+            Uint128::zero(),
+            Uint128::new(ONE),
+        );
+        assert_eq!(step, -25_000);
+    }
+
+    #[test]
+    fn step_integral_clamped() {
+        let mut state = PidState::default();
+        for _ in 0..5 {
+            state.step(
+                Uint128::new(1_100_000),
+                Uint128::zero(),
+                Uint128::new(ONE),
+                Uint128::zero(),
+                Uint128::new(250_000),
+            );
+        }
+        assert_eq!(state.integral, 250_000);
+
+        let step = state.step(
+            Uint128::new(ONE),
+            Uint128::zero(),
+            Uint128::new(ONE),
+            Uint128::zero(),
+            Uint128::new(250_000),
+        );
+        assert_eq!(step, 250_000);
+    }
+
+    #[test]
+    fn step_derivative() {
+        let mut state = PidState::default();
+        let gains = (Uint128::zero(), Uint128::zero(), Uint128::new(ONE));
+        state.step(
+            Uint128::new(1_020_000),
+            gains.0,
+            gains.1,
+            gains.2,
+            Uint128::zero(),
+        );
+        let step = state.step(
+            Uint128::new(1_050_000),
+            gains.0,
+            gains.1,
+            gains.2,
+            Uint128::zero(),
+        );
+        assert_eq!(step, 30_000);
+    }
+
+    #[test]
+    fn step_extreme_twap_does_not_panic() {
+        let mut state = PidState::default();
+        let step = state.step(
+            Uint128::MAX,
+            MAX_GAIN,
+            MAX_GAIN,
+            MAX_GAIN,
+            MAX_INTEGRAL_LIMIT,
+        );
+        assert!(step > 0);
+        assert_eq!(
+            apply_step(Uint128::new(ONE), step, Uint128::zero(), Uint128::new(ONE)),
+            Uint128::zero()
+        );
+
+        let step = state.step(
+            Uint128::zero(),
+            MAX_GAIN,
+            MAX_GAIN,
+            MAX_GAIN,
+            MAX_INTEGRAL_LIMIT,
+        );
+        assert!(step < 0);
+    }
+
+    #[test]
+    fn apply_step_clamps_ratio() {
+        let min = Uint128::new(ONE / 2);
+        let max = Uint128::new(ONE);
+        assert_eq!(
+            apply_step(Uint128::new(800_000), 100_000, min, max),
+            Uint128::new(700_000)
+        );
+        assert_eq!(apply_step(Uint128::new(800_000), 400_000, min, max), min);
+        assert_eq!(apply_step(Uint128::new(800_000), -400_000, min, max), max);
+        assert_eq!(apply_step(Uint128::new(800_000), i128::MIN, min, max), max);
+    }
+}
@@ -1,10 +1,23 @@
 use crate::{
-    helpers::{SwapPairUtils, Unit},
+    bond::BONDS,
+    helpers::Unit,
+    msg::{EpochConfigResponse, EpochInfoResponse, ExecuteMsg, MintAllowanceResponse},
+    oracle::{
+        chain_twap, get_leg_cumulatives, get_source_cumulatives, validate_sources, OracleSource,
+        PriceSource,
+    },
     pool::PoolConfig,
 };
-use cosmwasm_std::{Addr, Env, QuerierWrapper, Response, StdError, StdResult, Storage, Uint128};
-use cw20::{Cw20QueryMsg, TokenInfoResponse};
-use cw_storage_plus::Item;
+use cosmwasm_std::{
+    to_binary, Addr, Api, Env, Order, QuerierWrapper, Response, StdError, StdResult, Storage,
+    SubMsg, Uint128, WasmMsg,
+};
+use cw20::{Cw20ExecuteMsg, Cw20QueryMsg, TokenInfoResponse};
+use cw_storage_plus::{Bound, Item, Map, U64Key};
+use fantastic_boardroom::{
+    msg::{ExecuteMsg as BoardroomExecuteMsg, QueryMsg as BoardroomQueryMsg},
+    state::BoardroomInfo,
+};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
@@ -20,41 +33,149 @@ pub enum Error {
     #[error("Mint amount exceed allowance")]
     MintAmountTooLarge {},
 
+    #[error("No usable price source")]
+    PriceUnavailable {},
+
+    #[error("Bonds are only sold while synth is below peg")]
+    BondsNotAvailable {},
+
+    #[error("Bond redemption exceeds the epoch allowance")]
+    BondAllowanceExceeded {},
+
     #[error("Invalid config: {msg}")]
     InvalidConfig { msg: String },
 }
 
+/// recipients of the expansion minted by the protocol, rates are in
+/// precision unit and sum up to 1
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SeigniorageConfig {
+    pub treasury: Addr,
+    pub treasury_rate: Uint128,
+    pub dev_fund: Addr,
+    pub dev_fund_rate: Uint128,
+    /// staking contract funded with rewards, eg: fantastic-stake
+    pub staking: Addr,
+    pub staking_rate: Uint128,
+    /// staking is a fantastic-boardroom, notified of each allocation
+    #[serde(default)]
+    pub boardroom: bool,
+}
+
+/// reply to a failed boardroom allocation, the epoch goes on without it and
+/// the boardroom synth is not minted
+pub const ALLOCATE_SEIGNIORAGE_REPLY_ID: u64 = 3;
+
+impl SeigniorageConfig {
+    /// recipients must be valid addresses, a bad one fails every epoch mint
+    pub fn check(&self, api: &dyn Api) -> StdResult<()> {
+        for recipient in [&self.treasury, &self.dev_fund, &self.staking] {
+            api.addr_validate(recipient.as_str())?;
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        if self.treasury_rate.is_zero()
+            || self.dev_fund_rate.is_zero()
+            || self.staking_rate.is_zero()
+        {
+            return Err(Error::InvalidConfig {
+                msg: String::from("Seigniorage rates must be greater than zero"),
+            });
+        }
+        if self.treasury_rate + self.dev_fund_rate + self.staking_rate != Unit::precision() {
+            return Err(Error::InvalidConfig {
+                msg: String::from("Seigniorage rates must sum up to 1"),
+            });
+        }
+        if self.treasury == self.dev_fund
+            || self.treasury == self.staking
+            || self.dev_fund == self.staking
+        {
+            return Err(Error::InvalidConfig {
+                msg: String::from("Seigniorage recipients must be distinct"),
+            });
+        }
+        Ok(())
+    }
+
+    /// staking takes the rounding remainder
+    fn split(&self, amount: Uint128) -> SeigniorageSplit {
+        let treasury = amount.multiply_ratio(self.treasury_rate, Unit::precision());
+        let dev_fund = amount.multiply_ratio(self.dev_fund_rate, Unit::precision());
+        SeigniorageSplit {
+            treasury,
+            dev_fund,
+            staking: amount - treasury - dev_fund,
+        }
+    }
+}
+
+/// synth minted to each seigniorage recipient in an epoch
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct SeigniorageSplit {
+    pub treasury: Uint128,
+    pub dev_fund: Uint128,
+    pub staking: Uint128,
+}
+
 /// the mintable amount of the next epoch is calculated base on the TWAP
 /// in prior one
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct State {
-    pair_addr: Addr,
-    base_index: u8,
-    price_cumulative_last: Uint128,
+    sources: Vec<OracleSource>,
+    /// cumulative price of each source at epoch start, None if unusable
+    price_cumulatives_last: Vec<Option<Uint128>>,
+    quote_legs: Vec<PriceSource>,
+    quote_cumulatives_last: Vec<Uint128>,
     start_timestamp: u64,
     epoch_duration: u64,
     base_supply: Uint128,
     max_supply: Option<Uint128>,
     ceil_price: Option<Uint128>,
     max_expansion_rate: Option<Uint128>,
+    /// lower supply bound of each tier, the rate at the same index applies
+    /// up to the next bound
+    supply_tiers: Vec<Uint128>,
+    expansion_rates: Vec<Uint128>,
+    /// number of completed epochs
+    epoch: u64,
+    /// TWAP of the last completed epoch
+    last_twap: Option<Uint128>,
+    /// part of the epoch expansion kept for bond redemptions
+    bond_allowance: Uint128,
+    /// mint the expansion to the recipients at epoch start instead of
+    /// leaving it to minters
+    seigniorage: Option<SeigniorageConfig>,
+    last_seigniorage: Option<SeigniorageSplit>,
 }
 
 impl Default for State {
     fn default() -> Self {
         Self {
             epoch_duration: 0,
-            pair_addr: Addr::unchecked(""),
-            base_index: 0,
-            price_cumulative_last: Uint128::zero(),
+            sources: vec![],
+            price_cumulatives_last: vec![],
+            quote_legs: vec![],
+            quote_cumulatives_last: vec![],
             start_timestamp: 0,
             base_supply: Uint128::zero(),
             max_supply: None,
             ceil_price: None,
             max_expansion_rate: None,
+            supply_tiers: SUPPLY_TIERS.to_vec(),
+            expansion_rates: EXPANSION_RATES.iter().map(|&x| Uint128::new(x)).collect(),
+            epoch: 0,
+            last_twap: None,
+            bond_allowance: Uint128::zero(),
+            seigniorage: None,
+            last_seigniorage: None,
         }
     }
 }
 
+/// default tier table
 const SUPPLY_TIERS: [Uint128; 9] = [
     Unit::luna(0),
     Unit::luna(500_000),
@@ -69,6 +190,34 @@ const SUPPLY_TIERS: [Uint128; 9] = [
 
 const EXPANSION_RATES: [u128; 9] = [450, 400, 350, 300, 250, 200, 150, 125, 100];
 
+/// upper bound of a tier expansion rate, 10%
+const MAX_EXPANSION_RATE: Uint128 = Uint128::new(100_000);
+
+/// tiers start at zero and strictly increase, rates are bounded
+fn validate_tiers(supply_tiers: &[Uint128], expansion_rates: &[Uint128]) -> Result<(), Error> {
+    if supply_tiers.is_empty() || supply_tiers.len() != expansion_rates.len() {
+        return Err(Error::InvalidConfig {
+            msg: String::from("Supply tiers and expansion rates must have the same length"),
+        });
+    }
+    if !supply_tiers[0].is_zero() {
+        return Err(Error::InvalidConfig {
+            msg: String::from("First supply tier must be zero"),
+        });
+    }
+    if supply_tiers.windows(2).any(|x| x[0] >= x[1]) {
+        return Err(Error::InvalidConfig {
+            msg: String::from("Supply tiers must be strictly increasing"),
+        });
+    }
+    if expansion_rates.iter().any(|&x| x > MAX_EXPANSION_RATE) {
+        return Err(Error::InvalidConfig {
+            msg: format!("Expansion rate cannot exceed {}", MAX_EXPANSION_RATE),
+        });
+    }
+    Ok(())
+}
+
 impl State {
     fn get_expansion_rate(&self, supply: Uint128, twap: Uint128) -> Option<Uint128> {
         if self.ceil_price.is_none() {
@@ -76,36 +225,61 @@ impl State {
         } else if twap <= self.ceil_price.unwrap() {
             None
         } else {
-            let tier = SUPPLY_TIERS.iter().position(|&x| supply < x)?;
-            let &rate = EXPANSION_RATES.get(tier - 1)?;
+            // highest tier reached by the supply, the last one has no upper bound
+            let tier = self.supply_tiers.iter().rposition(|&x| supply >= x)?;
+            let &rate = self.expansion_rates.get(tier)?;
 
             if let Some(max) = self.max_expansion_rate {
-                Some(Uint128::from(rate).max(max))
+                Some(rate.min(max))
             } else {
-                Some(Uint128::from(rate))
+                Some(rate)
             }
         }
     }
 
-    fn next(&mut self, token_supply: Uint128, price_cumulative: Uint128, now: u64) -> Uint128 {
+    fn next(
+        &mut self,
+        token_supply: Uint128,
+        outstanding_bonds: Uint128,
+        price_cumulatives: Vec<Option<Uint128>>,
+        quote_cumulatives: Vec<Uint128>,
+        now: u64,
+    ) -> Result<Uint128, Error> {
         if self.start_timestamp == 0 {
             // first epoch
             self.start_timestamp = now;
-            self.price_cumulative_last = price_cumulative;
-            return Uint128::zero();
+            self.price_cumulatives_last = price_cumulatives;
+            self.quote_cumulatives_last = quote_cumulatives;
+            return Ok(Uint128::zero());
         }
 
-        let twap = (price_cumulative - self.price_cumulative_last)
-            / Uint128::from(now - self.start_timestamp);
+        let twap = chain_twap(
+            (&self.price_cumulatives_last, &self.quote_cumulatives_last),
+            (&price_cumulatives, &quote_cumulatives),
+            now - self.start_timestamp,
+        )?
+        .ok_or(Error::PriceUnavailable {})?;
 
         self.start_timestamp = now;
-        self.price_cumulative_last = price_cumulative;
+        self.price_cumulatives_last = price_cumulatives;
+        self.quote_cumulatives_last = quote_cumulatives;
+        self.epoch += 1;
+        self.last_twap = Some(twap);
         self.base_supply = token_supply;
         self.max_supply = self
             .get_expansion_rate(token_supply, twap)
             .or(Some(Uint128::zero()))
             .map(|x| token_supply.multiply_ratio(x + Unit::precision(), Unit::precision()));
-        twap
+        // bonds are redeemed first out of the expansion
+        self.bond_allowance = self
+            .max_supply
+            .map_or(Uint128::zero(), |x| x - token_supply)
+            .min(outstanding_bonds);
+        self.last_seigniorage = self.seigniorage.as_ref().map(|config| {
+            let expansion = self.max_supply.unwrap_or(token_supply) - token_supply;
+            config.split(expansion - self.bond_allowance)
+        });
+        Ok(twap)
     }
 
     fn get_allowed_supply(&self, now: u64) -> Option<Uint128> {
@@ -128,17 +302,41 @@ impl State {
     }
 }
 
-pub struct Epoch<'a>(Item<'a, State>);
+/// a completed epoch
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EpochRecord {
+    pub epoch: u64,
+    pub start_timestamp: u64,
+    pub end_timestamp: u64,
+    pub twap: Uint128,
+    /// synth supply at the end of the epoch
+    pub base_supply: Uint128,
+    /// supply the following epoch may expand to
+    pub max_supply: Option<Uint128>,
+    /// expansion minted by the protocol in seigniorage mode
+    pub seigniorage: Option<SeigniorageSplit>,
+}
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+pub struct Epoch<'a> {
+    state: Item<'a, State>,
+    history: Map<'a, U64Key, EpochRecord>,
+}
 
 const NAMESPACE: &str = "EPOCH";
 
 impl<'a> Epoch<'a> {
     pub const fn new() -> Self {
-        Self(Item::new(NAMESPACE))
+        Self {
+            state: Item::new(NAMESPACE),
+            history: Map::new("epoch_history"),
+        }
     }
 
     pub fn initialize(&self, storage: &mut dyn Storage) -> StdResult<()> {
-        self.0.save(storage, &State::default())?;
+        self.state.save(storage, &State::default())?;
         Ok(())
     }
 
@@ -156,7 +354,7 @@ impl<'a> Epoch<'a> {
         match state.get_allowed_supply(now) {
             None => Ok(()),
             Some(max_supply) => {
-                if current_supply + mint_amount > max_supply {
+                if current_supply + mint_amount + state.bond_allowance > max_supply {
                     Err(Error::MintAmountTooLarge {})
                 } else {
                     Ok(())
@@ -175,45 +373,258 @@ impl<'a> Epoch<'a> {
         let mut state = self.get(storage)?;
         let now = env.block.time.seconds();
 
-        if state.start_timestamp + state.epoch_duration > now {
+        // no time to average the price over in the block the epoch started
+        if state.start_timestamp + state.epoch_duration > now || state.start_timestamp >= now {
             return Err(Error::EpochNotElapsed {});
         }
 
-        let price_cumulative =
-            SwapPairUtils::query_cumulative_prices(querier, &state.pair_addr, state.base_index)?;
+        let last: Vec<Uint128> = state
+            .price_cumulatives_last
+            .iter()
+            .map(|x| x.unwrap_or_default())
+            .collect();
+        let price_cumulatives = get_source_cumulatives(
+            querier,
+            &state.sources,
+            &last,
+            now - state.start_timestamp,
+            now,
+        );
+        let quote_cumulatives = get_leg_cumulatives(
+            querier,
+            &state.quote_legs,
+            &state.quote_cumulatives_last,
+            now - state.start_timestamp,
+            now,
+        )?;
         let token_supply = Epoch::get_token_supply(querier, &pool.synth)?;
+        let outstanding_bonds = BONDS.get_state(storage)?.total_outstanding;
+
+        let start_timestamp = state.start_timestamp;
+        let epoch = state.epoch;
+        let twap = state.next(
+            token_supply,
+            outstanding_bonds,
+            price_cumulatives,
+            quote_cumulatives,
+            now,
+        )?;
+        self.state.save(storage, &state)?;
+
+        if state.epoch != epoch {
+            self.history.save(
+                storage,
+                U64Key::new(state.epoch),
+                &EpochRecord {
+                    epoch: state.epoch,
+                    start_timestamp,
+                    end_timestamp: now,
+                    twap,
+                    base_supply: state.base_supply,
+                    max_supply: state.max_supply,
+                    seigniorage: state.last_seigniorage.clone(),
+                },
+            )?;
+        }
 
-        let twap = state.next(token_supply, price_cumulative, now);
-        self.0.save(storage, &state)?;
+        let mut response = Response::new()
+            .add_attribute("action", "update_epoch")
+            .add_attribute("twap", twap)
+            .add_attribute("bond_allowance", state.bond_allowance);
+        if let (Some(config), Some(split)) = (&state.seigniorage, &state.last_seigniorage) {
+            if state.epoch != epoch {
+                response = response
+                    .add_attribute("seigniorage_treasury", split.treasury)
+                    .add_attribute("seigniorage_dev_fund", split.dev_fund)
+                    .add_attribute("seigniorage_staking", split.staking)
+                    .add_submessages(Epoch::mint_seigniorage(
+                        &pool.synth,
+                        &env.contract.address,
+                        state.epoch,
+                        config,
+                        split,
+                    )?);
+            }
+        }
+        Ok(response)
+    }
+
+    fn mint_seigniorage(
+        synth: &Addr,
+        this_addr: &Addr,
+        epoch: u64,
+        config: &SeigniorageConfig,
+        split: &SeigniorageSplit,
+    ) -> StdResult<Vec<SubMsg>> {
+        let mut recipients = vec![
+            (&config.treasury, split.treasury),
+            (&config.dev_fund, split.dev_fund),
+        ];
+        if !config.boardroom {
+            recipients.push((&config.staking, split.staking));
+        }
+        let mut messages = recipients
+            .into_iter()
+            .filter(|(_, amount)| !amount.is_zero())
+            .map(|(recipient, amount)| {
+                Ok(SubMsg::new(WasmMsg::Execute {
+                    contract_addr: synth.to_string(),
+                    msg: to_binary(&Cw20ExecuteMsg::Mint {
+                        recipient: recipient.to_string(),
+                        amount,
+                    })?,
+                    funds: vec![],
+                }))
+            })
+            .collect::<StdResult<Vec<_>>>()?;
+
+        // the boardroom snapshots every epoch, even when it receives nothing.
+        // Its synth is minted by the same call, a failing boardroom unwinds
+        // the mint and does not block the epoch
+        if config.boardroom {
+            messages.push(SubMsg::reply_on_error(
+                WasmMsg::Execute {
+                    contract_addr: this_addr.to_string(),
+                    msg: to_binary(&ExecuteMsg::AllocateSeigniorage {
+                        epoch,
+                        amount: split.staking,
+                    })?,
+                    funds: vec![],
+                },
+                ALLOCATE_SEIGNIORAGE_REPLY_ID,
+            ));
+        }
+        Ok(messages)
+    }
+
+    /// mint the staking seigniorage of `epoch` to the boardroom and allocate it
+    pub fn allocate_seigniorage(
+        &self,
+        storage: &dyn Storage,
+        synth: &Addr,
+        epoch: u64,
+        amount: Uint128,
+    ) -> Result<Response, Error> {
+        let boardroom = match self.get(storage)?.seigniorage {
+            Some(config) if config.boardroom => config.staking,
+            _ => {
+                return Err(Error::InvalidConfig {
+                    msg: String::from("Seigniorage is not allocated to a boardroom"),
+                })
+            }
+        };
+
+        let mut messages = vec![];
+        if !amount.is_zero() {
+            messages.push(WasmMsg::Execute {
+                contract_addr: synth.to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Mint {
+                    recipient: boardroom.to_string(),
+                    amount,
+                })?,
+                funds: vec![],
+            });
+        }
+        messages.push(WasmMsg::Execute {
+            contract_addr: boardroom.to_string(),
+            msg: to_binary(&BoardroomExecuteMsg::AllocateSeigniorage { epoch, amount })?,
+            funds: vec![],
+        });
 
         Ok(Response::new()
-            .add_attribute("action", "update_epoch")
-            .add_attribute("twap", twap))
+            .add_attribute("action", "allocate_seigniorage")
+            .add_attribute("epoch", epoch.to_string())
+            .add_attribute("amount", amount)
+            .add_messages(messages))
+    }
+
+    /// enable seigniorage with `config`, or disable it with None. A boardroom
+    /// must accept allocations from this pool and pay out its synth
+    pub fn config_seigniorage(
+        &self,
+        storage: &mut dyn Storage,
+        querier: &QuerierWrapper,
+        this_addr: &Addr,
+        pool: &PoolConfig,
+        config: Option<SeigniorageConfig>,
+    ) -> Result<Response, Error> {
+        if let Some(config) = &config {
+            config.validate()?;
+            if config.boardroom {
+                let boardroom: BoardroomInfo = querier
+                    .query_wasm_smart(&config.staking, &BoardroomQueryMsg::BoardroomInfo {})?;
+                if &boardroom.pool != this_addr || boardroom.synth_token != pool.synth {
+                    return Err(Error::InvalidConfig {
+                        msg: String::from("Boardroom is not set up for this pool"),
+                    });
+                }
+            }
+        }
+        let enabled = config.is_some();
+
+        self.state
+            .update(storage, |mut state| -> Result<_, StdError> {
+                state.seigniorage = config;
+                Ok(state)
+            })?;
+
+        Ok(Response::new()
+            .add_attribute("action", "config_seigniorage")
+            .add_attribute("enabled", enabled.to_string()))
+    }
+
+    /// synth paid per bond, the last epoch TWAP while it is below peg
+    pub fn get_bond_price(&self, storage: &dyn Storage) -> Result<Uint128, Error> {
+        let state = self.get(storage)?;
+        match state.last_twap {
+            Some(twap) if !twap.is_zero() && twap < Unit::precision() => Ok(twap),
+            _ => Err(Error::BondsNotAvailable {}),
+        }
+    }
+
+    pub fn get_bond_allowance(&self, storage: &dyn Storage) -> StdResult<Uint128> {
+        Ok(self.get(storage)?.bond_allowance)
+    }
+
+    /// consume `amount` of the bond allowance of the current epoch
+    pub fn use_bond_allowance(
+        &self,
+        storage: &mut dyn Storage,
+        amount: Uint128,
+    ) -> Result<(), Error> {
+        let mut state = self.get(storage)?;
+        if amount > state.bond_allowance {
+            return Err(Error::BondAllowanceExceeded {});
+        }
+        state.bond_allowance -= amount;
+        self.state.save(storage, &state)?;
+        Ok(())
     }
 
     pub fn config_oracle(
         &self,
         storage: &mut dyn Storage,
         querier: &QuerierWrapper,
-        pair_addr: &Addr,
-        base_index: u8,
+        sources: &[OracleSource],
+        quote_legs: &[PriceSource],
         now: u64,
     ) -> Result<(), Error> {
-        if base_index > 1 {
-            return Err(Error::Std(StdError::generic_err(
-                "Token index should be 0 or 1",
-            )));
+        validate_sources(sources)?;
+        for leg in quote_legs {
+            leg.validate()?;
         }
-        let cumulative_price =
-            SwapPairUtils::query_cumulative_prices(querier, &pair_addr, base_index)?;
-
-        self.0.update(storage, |mut state| -> Result<_, StdError> {
-            state.pair_addr = pair_addr.clone();
-            state.base_index = base_index;
-            state.start_timestamp = now;
-            state.price_cumulative_last = cumulative_price;
-            Ok(state)
-        })?;
+        let price_cumulatives = get_source_cumulatives(querier, sources, &[], 0, now);
+        let quote_cumulatives = get_leg_cumulatives(querier, quote_legs, &[], 0, now)?;
+
+        self.state
+            .update(storage, |mut state| -> Result<_, StdError> {
+                state.sources = sources.to_vec();
+                state.quote_legs = quote_legs.to_vec();
+                state.quote_cumulatives_last = quote_cumulatives;
+                state.start_timestamp = now;
+                state.price_cumulatives_last = price_cumulatives;
+                Ok(state)
+            })?;
         Ok(())
     }
 
@@ -223,25 +634,107 @@ impl<'a> Epoch<'a> {
         epoch_duration: u64,
         ceil_price: Option<Uint128>,
         max_expansion_rate: Option<Uint128>,
+        supply_tiers: Option<Vec<Uint128>>,
+        expansion_rates: Option<Vec<Uint128>>,
     ) -> Result<Response, Error> {
         if ceil_price.is_some() && ceil_price.unwrap() < Unit::precision() {
             return Err(Error::InvalidConfig {
                 msg: String::from("Ceil price cannot be lower than 1"),
             });
         }
-
-        self.0.update(storage, |mut state| -> Result<_, StdError> {
-            state.ceil_price = ceil_price;
-            state.max_expansion_rate = max_expansion_rate;
-            state.epoch_duration = epoch_duration;
-            Ok(state)
-        })?;
+        let tiers = match (supply_tiers, expansion_rates) {
+            (None, None) => None,
+            (Some(supply_tiers), Some(expansion_rates)) => {
+                validate_tiers(&supply_tiers, &expansion_rates)?;
+                Some((supply_tiers, expansion_rates))
+            }
+            _ => {
+                return Err(Error::InvalidConfig {
+                    msg: String::from("Supply tiers and expansion rates must be set together"),
+                })
+            }
+        };
+
+        self.state
+            .update(storage, |mut state| -> Result<_, StdError> {
+                state.ceil_price = ceil_price;
+                state.max_expansion_rate = max_expansion_rate;
+                state.epoch_duration = epoch_duration;
+                if let Some((supply_tiers, expansion_rates)) = tiers {
+                    state.supply_tiers = supply_tiers;
+                    state.expansion_rates = expansion_rates;
+                }
+                Ok(state)
+            })?;
 
         Ok(Response::new()
             .add_attribute("action", "config_epoch")
             .add_attribute("epoch_duration", epoch_duration.to_string()))
     }
 
+    pub fn get_config(&self, storage: &dyn Storage) -> StdResult<EpochConfigResponse> {
+        let state = self.get(storage)?;
+        Ok(EpochConfigResponse {
+            epoch_duration: state.epoch_duration,
+            ceil_price: state.ceil_price,
+            max_expansion_rate: state.max_expansion_rate,
+            supply_tiers: state.supply_tiers,
+            expansion_rates: state.expansion_rates,
+            seigniorage: state.seigniorage,
+        })
+    }
+
+    pub fn get_info(&self, storage: &dyn Storage, now: u64) -> StdResult<EpochInfoResponse> {
+        let state = self.get(storage)?;
+        Ok(EpochInfoResponse {
+            epoch: state.epoch,
+            start_timestamp: state.start_timestamp,
+            next_epoch_timestamp: state.start_timestamp + state.epoch_duration,
+            last_twap: state.last_twap,
+            base_supply: state.base_supply,
+            max_supply: state.max_supply,
+            allowed_supply: state.get_allowed_supply(now),
+            bond_allowance: state.bond_allowance,
+            seigniorage: state.last_seigniorage,
+        })
+    }
+
+    /// synth still mintable now, None when unlimited
+    pub fn get_mint_allowance(
+        &self,
+        storage: &dyn Storage,
+        querier: &QuerierWrapper,
+        pool: &PoolConfig,
+        now: u64,
+    ) -> StdResult<MintAllowanceResponse> {
+        let state = self.get(storage)?;
+        let current_supply = Epoch::get_token_supply(querier, &pool.synth)?;
+        let allowed_supply = state.get_allowed_supply(now);
+
+        Ok(MintAllowanceResponse {
+            current_supply,
+            allowed_supply,
+            remaining: allowed_supply
+                .map(|x| x.saturating_sub(current_supply + state.bond_allowance)),
+        })
+    }
+
+    /// completed epochs in ascending order
+    pub fn get_history(
+        &self,
+        storage: &dyn Storage,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<Vec<EpochRecord>> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after.map(Bound::exclusive_int);
+        self.history
+            .range(storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|item| item.map(|(_, record)| record))
+            .collect()
+    }
+
     fn get_token_supply(querier: &QuerierWrapper, token: &Addr) -> Result<Uint128, StdError> {
         let TokenInfoResponse { total_supply, .. } =
             querier.query_wasm_smart(token, &Cw20QueryMsg::TokenInfo {})?;
@@ -249,8 +742,181 @@ impl<'a> Epoch<'a> {
     }
 
     fn get(&self, storage: &dyn Storage) -> StdResult<State> {
-        self.0.load(storage)
+        self.state.load(storage)
     }
 }
 
 pub const EPOCH: Epoch = Epoch::new();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{mock_env, MockStorage};
+    use cosmwasm_std::{
+        from_binary, from_slice, Binary, ContractResult, CosmosMsg, Empty, Querier, QuerierResult,
+        QueryRequest, ReplyOn, SystemResult, Timestamp, WasmQuery,
+    };
+
+    /// answers the synth token info query with a fixed supply
+    struct SupplyQuerier(Uint128);
+
+    impl Querier for SupplyQuerier {
+        fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+            match from_slice(bin_request).unwrap() {
+                QueryRequest::<Empty>::Wasm(WasmQuery::Smart { .. }) => {
+                    SystemResult::Ok(ContractResult::Ok(
+                        to_binary(&TokenInfoResponse {
+                            name: "synth".to_string(),
+                            symbol: "SYN".to_string(),
+                            decimals: 6,
+                            total_supply: self.0,
+                        })
+                        .unwrap(),
+                    ))
+                }
+                _ => panic!("unexpected query"),
+            }
+        }
+    }
+
+    fn wasm_execute(msg: &SubMsg) -> (&str, &Binary) {
+        match &msg.msg {
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr, msg, ..
+            }) => (contract_addr, msg),
+            _ => panic!("unexpected message"),
+        }
+    }
+
+    #[test]
+    fn expansion_rate_by_tier() {
+        let mut state = State {
+            ceil_price: Some(Unit::precision()),
+            ..Default::default()
+        };
+        let above_peg = Uint128::new(1_100_000);
+
+        assert_eq!(
+            state.get_expansion_rate(Unit::luna(100), Unit::precision()),
+            None
+        );
+        assert_eq!(
+            state.get_expansion_rate(Unit::luna(100), above_peg),
+            Some(Uint128::new(450))
+        );
+        assert_eq!(
+            state.get_expansion_rate(Unit::luna(500_000), above_peg),
+            Some(Uint128::new(400))
+        );
+        // at and beyond the last tier
+        assert_eq!(
+            state.get_expansion_rate(Unit::luna(50_000_000), above_peg),
+            Some(Uint128::new(100))
+        );
+        assert_eq!(
+            state.get_expansion_rate(Unit::luna(900_000_000), above_peg),
+            Some(Uint128::new(100))
+        );
+
+        state.max_expansion_rate = Some(Uint128::new(300));
+        assert_eq!(
+            state.get_expansion_rate(Unit::luna(100), above_peg),
+            Some(Uint128::new(300))
+        );
+        assert_eq!(
+            state.get_expansion_rate(Unit::luna(900_000_000), above_peg),
+            Some(Uint128::new(100))
+        );
+    }
+
+    #[test]
+    fn boardroom_seigniorage_minted_with_its_allocation() {
+        let mut storage = MockStorage::new();
+        let querier = SupplyQuerier(Unit::luna(1_000_000));
+        let querier = QuerierWrapper::new(&querier);
+        let now = 10_000;
+        EPOCH.initialize(&mut storage).unwrap();
+        EPOCH
+            .config_epoch(
+                &mut storage,
+                3600,
+                Some(Unit::precision()),
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        let sources = [OracleSource {
+            source: PriceSource::Fixed {
+                price: Uint128::new(1_100_000),
+            },
+            min_liquidity: Uint128::zero(),
+        }];
+        EPOCH
+            .config_oracle(&mut storage, &querier, &sources, &[], now - 3600)
+            .unwrap();
+        EPOCH
+            .state
+            .update(&mut storage, |mut state| -> StdResult<_> {
+                state.seigniorage = Some(SeigniorageConfig {
+                    treasury: Addr::unchecked("treasury"),
+                    treasury_rate: Uint128::new(200_000),
+                    dev_fund: Addr::unchecked("dev_fund"),
+                    dev_fund_rate: Uint128::new(100_000),
+                    staking: Addr::unchecked("boardroom"),
+                    staking_rate: Uint128::new(700_000),
+                    boardroom: true,
+                });
+                Ok(state)
+            })
+            .unwrap();
+
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(now);
+        let mut pool = PoolConfig::init("uusd".to_string(), Addr::unchecked("router"), vec![]);
+        pool.synth = Addr::unchecked("synth");
+        let res = EPOCH
+            .next_epoch(&mut storage, &querier, env.clone(), &pool)
+            .unwrap();
+        let split = EPOCH.get(&storage).unwrap().last_seigniorage.unwrap();
+        assert!(!split.staking.is_zero());
+
+        // treasury and dev fund are minted directly, the boardroom share only
+        // through the call that also allocates it
+        assert_eq!(res.messages.len(), 3);
+        for msg in &res.messages[..2] {
+            assert_eq!(msg.reply_on, ReplyOn::Never);
+            assert_eq!(wasm_execute(msg).0, "synth");
+        }
+        let allocate = &res.messages[2];
+        assert_eq!(allocate.reply_on, ReplyOn::Error);
+        assert_eq!(allocate.id, ALLOCATE_SEIGNIORAGE_REPLY_ID);
+        let (contract_addr, msg) = wasm_execute(allocate);
+        assert_eq!(contract_addr, env.contract.address.as_str());
+        assert_eq!(
+            from_binary::<ExecuteMsg>(msg).unwrap(),
+            ExecuteMsg::AllocateSeigniorage {
+                epoch: 1,
+                amount: split.staking,
+            }
+        );
+
+        let res = EPOCH
+            .allocate_seigniorage(&storage, &pool.synth, 1, split.staking)
+            .unwrap();
+        assert_eq!(
+            res.messages
+                .iter()
+                .map(|x| wasm_execute(x).0)
+                .collect::<Vec<_>>(),
+            vec!["synth", "boardroom"]
+        );
+        assert_eq!(
+            from_binary::<Cw20ExecuteMsg>(wasm_execute(&res.messages[0]).1).unwrap(),
+            Cw20ExecuteMsg::Mint {
+                recipient: "boardroom".to_string(),
+                amount: split.staking,
+            }
+        );
+    }
+}
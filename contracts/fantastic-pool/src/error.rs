@@ -1,4 +1,4 @@
-use cosmwasm_std::{Addr, StdError, Timestamp};
+use cosmwasm_std::{Addr, StdError, Timestamp, Uint128};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -15,6 +15,9 @@ pub enum ContractError {
     #[error("Epoch:{0}")]
     Epoch(#[from] crate::epoch::Error),
 
+    #[error("Unauthorized")]
+    Unauthorized {},
+
     #[error("Share address is already set")]
     ShareAlreadySet {},
 
@@ -24,12 +27,33 @@ pub enum ContractError {
     #[error("Price is unvailable or outdated")]
     PriceUnavailableOrOutdated {},
 
+    #[error("Not enough observations to cover a {window}s window")]
+    TwapWindowNotCovered { window: u64 },
+
+    #[error("Spot price {spot} deviates too much from TWAP {twap}")]
+    PriceDeviationTooLarge { spot: Uint128, twap: Uint128 },
+
     #[error("Minting is paused")]
     MintingPaused {},
 
     #[error("Redemption is paused")]
     RedemptionPaused {},
 
+    #[error("Recollateralize is paused")]
+    RecollateralizePaused {},
+
+    #[error("Buyback is paused")]
+    BuybackPaused {},
+
+    #[error("Recollateralize amount exceeds the collateral deficit")]
+    RecollateralizeTooLarge {},
+
+    #[error("Buyback amount exceeds the excess collateral")]
+    BuybackTooLarge {},
+
+    #[error("Incorrect share token, want {want}, user send {send}")]
+    BuybackInvalidShareInput { want: Addr, send: Addr },
+
     #[error("Slippage reached")]
     SlippageReached {},
 
@@ -45,12 +69,39 @@ pub enum ContractError {
     #[error("Cannot redeem zero amount")]
     RedeemEmptyAmount {},
 
+    #[error("Incorrect synth token for bond, want {want}, user send {send}")]
+    BondInvalidSynthInput { want: Addr, send: Addr },
+
+    #[error("Bond amount cannot be zero")]
+    BondEmptyAmount {},
+
+    #[error("Bond claim of {claim} is too small")]
+    InsufficientBondClaim { claim: Uint128 },
+
     #[error("Collect and mint/redeem cannot happen in the same block")]
     CollectTooEarly {},
 
+    #[error("Invalid collateral policy: {msg}")]
+    InvalidCollateralPolicy { msg: String },
+
     #[error("Collateral ratio is cooling down")]
     CollateralRatioRefreshCooldown,
 
+    #[error("Collateral {asset} is not registered")]
+    CollateralNotRegistered { asset: String },
+
+    #[error("Collateral {asset} is disabled")]
+    CollateralDisabled { asset: String },
+
+    #[error("Collateral {asset} exceeds its weight cap")]
+    CollateralWeightExceeded { asset: String },
+
+    #[error("Not enough {asset} collateral in the pool")]
+    InsufficientCollateral { asset: String },
+
+    #[error("Fee recipient is not set")]
+    FeeRecipientNotSet {},
+
     #[error("Cannot update TWAP before {time}")]
     TwapPeriodNotElapsed { time: Timestamp },
 }
@@ -0,0 +1,192 @@
+use astroport::asset::Asset;
+use cosmwasm_std::{Addr, CosmosMsg, Response, StdError, StdResult, Storage, Uint128};
+use cw_storage_plus::Item;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    collateral::{add_asset, transfer_asset, COLLATERALS},
+    helpers::Unit,
+    ContractError,
+};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeRecipient {
+    pub address: Addr,
+    /// part of the fee sent to this recipient, all weights sum up to 1
+    pub weight: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct FeeState {
+    pub recipients: Vec<FeeRecipient>,
+    /// cumulative amount of fee distributed, per collateral
+    pub total_distributed: Vec<Asset>,
+}
+
+pub struct Fee<'a>(Item<'a, FeeState>);
+
+impl<'a> Fee<'a> {
+    pub const fn new() -> Self {
+        Fee(Item::new("fee"))
+    }
+
+    pub fn initialize(&self, storage: &mut dyn Storage) -> StdResult<()> {
+        self.0.save(storage, &FeeState::default())
+    }
+
+    pub fn get_state(&self, storage: &dyn Storage) -> StdResult<FeeState> {
+        self.0.load(storage)
+    }
+
+    /// set the fee split. Allow from admin only
+    pub fn set_recipients(
+        &self,
+        storage: &mut dyn Storage,
+        recipients: Vec<FeeRecipient>,
+    ) -> Result<Response, ContractError> {
+        let total_weight = recipients
+            .iter()
+            .fold(Uint128::zero(), |acc, x| acc + x.weight);
+        if recipients.is_empty() || total_weight != Unit::precision() {
+            return Err(ContractError::Std(StdError::generic_err(
+                "Fee recipient weights must sum up to 1",
+            )));
+        }
+        if recipients.iter().any(|x| x.weight.is_zero()) {
+            return Err(ContractError::Std(StdError::generic_err(
+                "Fee recipient weight must be greater than zero",
+            )));
+        }
+        for (i, recipient) in recipients.iter().enumerate() {
+            if recipients[..i]
+                .iter()
+                .any(|x| x.address == recipient.address)
+            {
+                return Err(ContractError::Std(StdError::generic_err(format!(
+                    "Duplicate fee recipient {}",
+                    recipient.address
+                ))));
+            }
+        }
+
+        self.0.update(storage, |mut state| -> StdResult<_> {
+            state.recipients = recipients;
+            Ok(state)
+        })?;
+
+        Ok(Response::new().add_attribute("action", "set_fee_recipients"))
+    }
+
+    /// send all accrued collateral fee to the recipients
+    pub fn distribute(&self, storage: &mut dyn Storage) -> Result<Response, ContractError> {
+        let mut state = self.0.load(storage)?;
+        if state.recipients.is_empty() {
+            return Err(ContractError::FeeRecipientNotSet {});
+        }
+
+        let mut messages: Vec<CosmosMsg> = vec![];
+        let mut distributed: Vec<Asset> = vec![];
+        for mut collateral in COLLATERALS.list(storage)? {
+            let fee = collateral.total_fee;
+            if fee.is_zero() {
+                continue;
+            }
+
+            // last recipient takes the rounding remainder
+            let mut remaining = fee;
+            for (i, recipient) in state.recipients.iter().enumerate() {
+                let amount = if i + 1 == state.recipients.len() {
+                    remaining
+                } else {
+                    fee.multiply_ratio(recipient.weight, Unit::precision())
+                };
+                remaining -= amount;
+                if !amount.is_zero() {
+                    messages.push(transfer_asset(
+                        &collateral.asset,
+                        amount,
+                        &recipient.address,
+                    )?);
+                }
+            }
+
+            add_asset(&mut state.total_distributed, &collateral.asset, fee);
+            add_asset(&mut distributed, &collateral.asset, fee);
+            collateral.total_fee = Uint128::zero();
+            COLLATERALS.save(storage, &collateral)?;
+        }
+        self.0.save(storage, &state)?;
+
+        let distributed_attr = distributed
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<String>>()
+            .join(",");
+
+        Ok(Response::new()
+            .add_attribute("action", "distribute_fees")
+            .add_attribute("amount", distributed_attr)
+            .add_messages(messages))
+    }
+}
+
+pub const FEE: Fee = Fee::new();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    fn recipient(address: &str, weight: u128) -> FeeRecipient {
+        FeeRecipient {
+            address: Addr::unchecked(address),
+            weight: Uint128::new(weight),
+        }
+    }
+
+    fn assert_rejected(storage: &mut dyn Storage, recipients: Vec<FeeRecipient>, msg: &str) {
+        match FEE.set_recipients(storage, recipients) {
+            Err(ContractError::Std(StdError::GenericErr { msg: err, .. })) => {
+                assert_eq!(err, msg)
+            }
+            res => panic!("unexpected result {:?}", res),
+        }
+    }
+
+    #[test]
+    fn set_recipients_validation() {
+        let mut storage = MockStorage::new();
+        FEE.initialize(&mut storage).unwrap();
+
+        assert_rejected(
+            &mut storage,
+            vec![],
+            "Fee recipient weights must sum up to 1",
+        );
+        assert_rejected(
+            &mut storage,
+            vec![recipient("alice", 600_000), recipient("bob", 300_000)],
+            "Fee recipient weights must sum up to 1",
+        );
+        assert_rejected(
+            &mut storage,
+            vec![recipient("alice", 1_000_000), recipient("bob", 0)],
+            "Fee recipient weight must be greater than zero",
+        );
+        assert_rejected(
+            &mut storage,
+            vec![
+                recipient("alice", 500_000),
+                recipient("bob", 200_000),
+                recipient("alice", 300_000),
+            ],
+            "Duplicate fee recipient alice",
+        );
+
+        let recipients = vec![recipient("alice", 700_000), recipient("bob", 300_000)];
+        FEE.set_recipients(&mut storage, recipients.clone())
+            .unwrap();
+        assert_eq!(FEE.get_state(&storage).unwrap().recipients, recipients);
+    }
+}
@@ -1,5 +1,8 @@
-use astroport::pair::CumulativePricesResponse;
+use astroport::asset::Asset;
+use astroport::pair::{CumulativePricesResponse, PoolResponse, SimulationResponse};
 use cosmwasm_std::{Addr, QuerierWrapper, StdError, Uint128};
+use cw20::{BalanceResponse, Cw20QueryMsg, TokenInfoResponse};
+use std::convert::TryInto;
 
 pub struct SwapPairUtils;
 
@@ -20,11 +23,74 @@ impl SwapPairUtils {
 
         Ok(cumulative_price)
     }
+
+    /// reserves of the base and quote asset
+    pub fn query_reserves(
+        querier: &QuerierWrapper,
+        pair_addr: &Addr,
+        base_index: u8,
+    ) -> Result<(Asset, Asset), StdError> {
+        let PoolResponse { assets, .. } =
+            querier.query_wasm_smart(pair_addr, &astroport::pair::QueryMsg::Pool {})?;
+
+        let base_index = usize::from(base_index);
+        let quote_index = (base_index + 1) % 2; // since assets length alway be 2
+        let base = assets
+            .get(base_index)
+            .ok_or_else(|| StdError::generic_err("invalid pool info"))?;
+        let quote = assets
+            .get(quote_index)
+            .ok_or_else(|| StdError::generic_err("invalid pool info"))?;
+        Ok((base.clone(), quote.clone()))
+    }
+
+    /// quote amount received for `offer_asset`, before commission
+    pub fn query_simulation(
+        querier: &QuerierWrapper,
+        pair_addr: &Addr,
+        offer_asset: Asset,
+    ) -> Result<Uint128, StdError> {
+        let SimulationResponse {
+            return_amount,
+            commission_amount,
+            ..
+        } = querier.query_wasm_smart(
+            pair_addr,
+            &astroport::pair::QueryMsg::Simulation { offer_asset },
+        )?;
+        Ok(return_amount + commission_amount)
+    }
+}
+
+pub struct TokenUtils;
+
+impl TokenUtils {
+    pub fn query_supply(querier: &QuerierWrapper, token: &Addr) -> Result<Uint128, StdError> {
+        let TokenInfoResponse { total_supply, .. } =
+            querier.query_wasm_smart(token, &Cw20QueryMsg::TokenInfo {})?;
+        Ok(total_supply)
+    }
+
+    pub fn query_balance(
+        querier: &QuerierWrapper,
+        token: &Addr,
+        address: &Addr,
+    ) -> Result<Uint128, StdError> {
+        let BalanceResponse { balance } = querier.query_wasm_smart(
+            token,
+            &Cw20QueryMsg::Balance {
+                address: address.to_string(),
+            },
+        )?;
+        Ok(balance)
+    }
 }
 
 pub struct Unit;
 
 impl Unit {
+    pub const BPS_PRECISION: u64 = 10_000;
+
     pub const fn luna(value: u128) -> Uint128 {
         Uint128::new(value * 10u128.pow(6))
     }
@@ -33,3 +99,31 @@ impl Unit {
         Uint128::new(1_000_000u128)
     }
 }
+
+// (de)serialize i128, see https://github.com/CosmWasm/cosmwasm/issues/1114
+pub mod int128 {
+    use serde::{self, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(bigint: &i128, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&bigint.to_string())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<i128, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let str = String::deserialize(deserializer)?;
+        str::parse::<i128>(&str).map_err(serde::de::Error::custom)
+    }
+}
+
+pub fn to_i128(n: Uint128) -> i128 {
+    n.u128().try_into().unwrap()
+}
+
+pub fn to_uint128(n: i128) -> Uint128 {
+    n.try_into().map(Uint128::new).unwrap()
+}
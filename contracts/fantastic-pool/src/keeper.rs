@@ -0,0 +1,179 @@
+use astroport::asset::AssetInfo;
+use cosmwasm_std::{Addr, CosmosMsg, Response, StdError, StdResult, Storage, Uint128};
+use cw_storage_plus::Item;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    collateral::{transfer_asset, COLLATERALS},
+    ContractError,
+};
+
+/// maintenance calls rewarded with a bounty
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum KeeperAction {
+    UpdateOracle,
+    UpdateEpoch,
+    RefreshCollateralRatio,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct KeeperState {
+    /// asset bounties are paid in, the share token or a registered collateral
+    pub reward_asset: Option<AssetInfo>,
+    pub update_oracle_reward: Uint128,
+    pub update_epoch_reward: Uint128,
+    pub refresh_collateral_ratio_reward: Uint128,
+    /// amount funded by admin and not yet paid
+    pub budget: Uint128,
+    pub total_paid: Uint128,
+}
+
+impl KeeperState {
+    pub fn get_reward(&self, action: &KeeperAction) -> Uint128 {
+        match action {
+            KeeperAction::UpdateOracle => self.update_oracle_reward,
+            KeeperAction::UpdateEpoch => self.update_epoch_reward,
+            KeeperAction::RefreshCollateralRatio => self.refresh_collateral_ratio_reward,
+        }
+    }
+}
+
+pub struct Keeper<'a>(Item<'a, KeeperState>);
+
+impl<'a> Keeper<'a> {
+    pub const fn new() -> Self {
+        Keeper(Item::new("keeper"))
+    }
+
+    pub fn initialize(&self, storage: &mut dyn Storage) -> StdResult<()> {
+        self.0.save(storage, &KeeperState::default())
+    }
+
+    pub fn get_state(&self, storage: &dyn Storage) -> StdResult<KeeperState> {
+        self.0.load(storage)
+    }
+
+    /// budget held for `asset`, to be kept out of collateral and burnable share
+    pub fn get_reserved(&self, storage: &dyn Storage, asset: &AssetInfo) -> StdResult<Uint128> {
+        let state = self.0.load(storage)?;
+        match state.reward_asset {
+            Some(reward_asset) if reward_asset.equal(asset) => Ok(state.budget),
+            _ => Ok(Uint128::zero()),
+        }
+    }
+
+    /// set bounty asset and amounts. Allow from admin only
+    pub fn config(
+        &self,
+        storage: &mut dyn Storage,
+        share: &Addr,
+        reward_asset: AssetInfo,
+        update_oracle_reward: Uint128,
+        update_epoch_reward: Uint128,
+        refresh_collateral_ratio_reward: Uint128,
+    ) -> Result<Response, ContractError> {
+        let is_share = reward_asset.equal(&AssetInfo::Token {
+            contract_addr: share.clone(),
+        });
+        if !is_share {
+            COLLATERALS.get(storage, &reward_asset)?;
+        }
+
+        let mut state = self.0.load(storage)?;
+        if let Some(current) = &state.reward_asset {
+            if !current.equal(&reward_asset) && !state.budget.is_zero() {
+                return Err(ContractError::Std(StdError::generic_err(
+                    "Keeper budget must be spent before changing reward asset",
+                )));
+            }
+        }
+        state.reward_asset = Some(reward_asset.clone());
+        state.update_oracle_reward = update_oracle_reward;
+        state.update_epoch_reward = update_epoch_reward;
+        state.refresh_collateral_ratio_reward = refresh_collateral_ratio_reward;
+        self.0.save(storage, &state)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "config_keeper")
+            .add_attribute("reward_asset", reward_asset.to_string())
+            .add_attribute("update_oracle_reward", update_oracle_reward)
+            .add_attribute("update_epoch_reward", update_epoch_reward)
+            .add_attribute(
+                "refresh_collateral_ratio_reward",
+                refresh_collateral_ratio_reward,
+            ))
+    }
+
+    /// add `amount` of `asset` sent by admin to the budget
+    pub fn fund(
+        &self,
+        storage: &mut dyn Storage,
+        asset: &AssetInfo,
+        amount: Uint128,
+    ) -> Result<Response, ContractError> {
+        let mut state = self.0.load(storage)?;
+        match &state.reward_asset {
+            Some(reward_asset) if reward_asset.equal(asset) => {}
+            _ => {
+                return Err(ContractError::Std(StdError::generic_err(
+                    "Asset is not the keeper reward asset",
+                )))
+            }
+        }
+        if amount.is_zero() {
+            return Err(ContractError::Std(StdError::generic_err("Nothing to fund")));
+        }
+
+        state.budget += amount;
+        self.0.save(storage, &state)?;
+        self.reserve_collateral(storage, asset, state.budget)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "fund_keeper")
+            .add_attribute("amount", amount)
+            .add_attribute("budget", state.budget))
+    }
+
+    /// pay the bounty of `action` to `keeper`, nothing is paid once the budget
+    /// cannot cover it
+    pub fn reward(
+        &self,
+        storage: &mut dyn Storage,
+        action: KeeperAction,
+        keeper: &Addr,
+    ) -> StdResult<Option<CosmosMsg>> {
+        let mut state = self.0.load(storage)?;
+        let amount = state.get_reward(&action);
+        let reward_asset = match &state.reward_asset {
+            Some(reward_asset) if !amount.is_zero() && amount <= state.budget => {
+                reward_asset.clone()
+            }
+            _ => return Ok(None),
+        };
+
+        state.budget -= amount;
+        state.total_paid += amount;
+        self.0.save(storage, &state)?;
+        self.reserve_collateral(storage, &reward_asset, state.budget)?;
+
+        Ok(Some(transfer_asset(&reward_asset, amount, keeper)?))
+    }
+
+    /// keep the budget out of the collateral holding when paid in collateral
+    fn reserve_collateral(
+        &self,
+        storage: &mut dyn Storage,
+        asset: &AssetInfo,
+        budget: Uint128,
+    ) -> StdResult<()> {
+        if let Ok(mut collateral) = COLLATERALS.get(storage, asset) {
+            collateral.total_reserved = budget;
+            COLLATERALS.save(storage, &collateral)?;
+        }
+        Ok(())
+    }
+}
+
+pub const KEEPER: Keeper = Keeper::new();
@@ -1,7 +1,12 @@
+mod bond;
+pub mod collateral;
 pub mod contract;
+mod controller;
 mod epoch;
 mod error;
+mod fee;
 mod helpers;
+mod keeper;
 pub mod msg;
 mod oracle;
 pub mod pool;
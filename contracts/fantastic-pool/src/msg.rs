@@ -1,4 +1,10 @@
-use crate::oracle::PairOracleState;
+use crate::collateral::{CollateralInfo, CollateralPrice};
+use crate::controller::{PidState, RatioController};
+use crate::epoch::{EpochRecord, SeigniorageConfig, SeigniorageSplit};
+use crate::fee::FeeRecipient;
+use crate::oracle::{OracleKind, OracleSource, PairOracleState, PriceMode, PriceSource};
+use crate::pool::{CollateralRatioRecord, UserEntry};
+use astroport::asset::{Asset, AssetInfo};
 use cosmwasm_std::{Addr, Uint128};
 use cw20::Cw20ReceiveMsg;
 use schemars::JsonSchema;
@@ -13,6 +19,14 @@ pub struct InstantiateMsg {
     pub share_symbol: String,
     pub share_name: String,
     pub share_max_cap: Uint128,
+    /// astroport router used to buy back share
+    pub router: Addr,
+    /// intermediate assets between collateral and share, empty for direct swap
+    pub swap_route: Option<Vec<AssetInfo>>,
+    /// collateral policy, default value is used if not set
+    pub price_band: Option<Uint128>,
+    pub collateral_ratio_step: Option<Uint128>,
+    pub refresh_collateral_ratio_cooldown: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -23,10 +37,22 @@ pub struct MigrateMsg {}
 pub enum ExecuteMsg {
     Mint {
         min_synth_out: Uint128,
+        /// tighten the minimum share received by the buyback swap
+        min_share_bought: Option<Uint128>,
     },
     Receive(Cw20ReceiveMsg),
+    /// deposit collateral to an under-collateralized pool for share
+    Recollateralize {
+        min_share_out: Uint128,
+    },
     Collect {},
+    /// mint synth for bonds out of the current expansion
+    RedeemBond {
+        amount: Uint128,
+    },
     UpdateOracle {},
+    /// run every due maintenance step, response data is a `PokeResponse`
+    Poke {},
     SetFee {
         minting_fee: Uint128,
         redemption_fee: Uint128,
@@ -38,39 +64,141 @@ pub enum ExecuteMsg {
     Toggle {
         mint_paused: bool,
         redeem_paused: bool,
+        recollateralize_paused: Option<bool>,
+        buyback_paused: Option<bool>,
+    },
+    SetSharePricing {
+        mode: PriceMode,
+        max_twap_age: u64,
+    },
+    SetRecollateralizeConfig {
+        recollateralize_bonus: Uint128,
+        buyback_fee: Uint128,
     },
     SetMinCollateralRatio {
         value: Uint128,
     },
+    SetRatioController {
+        controller: RatioController,
+    },
+    /// update fields of the collateral ratio policy, unset fields are kept
+    UpdateCollateralPolicy {
+        price_band: Option<Uint128>,
+        collateral_ratio_step: Option<Uint128>,
+        refresh_collateral_ratio_cooldown: Option<u64>,
+    },
+    SetRouter {
+        router: Addr,
+        swap_route: Vec<AssetInfo>,
+    },
+    SetBuybackSlippage {
+        max_slippage_bps: u64,
+    },
+    /// register a collateral or update an existing one
+    ConfigCollateral {
+        asset: AssetInfo,
+        price: CollateralPrice,
+        weight_cap: Uint128,
+        enabled: bool,
+        swap_route: Option<Vec<AssetInfo>>,
+    },
     ConfigShareOracle {
-        pair_addr: Addr,
-        base_index: u8,
+        /// the oracle reports the median of the usable sources
+        sources: Vec<OracleSource>,
+        /// legs converting the quote asset into the reference currency
+        quote_legs: Option<Vec<PriceSource>>,
         twap_period: u64,
     },
     ConfigSynthOracle {
-        pair_addr: Addr,
-        base_index: u8,
+        sources: Vec<OracleSource>,
+        quote_legs: Option<Vec<PriceSource>>,
+        twap_period: u64,
+    },
+    /// oracle pricing `asset`, used once the collateral is set to `CollateralPrice::Oracle`
+    ConfigCollateralOracle {
+        asset: AssetInfo,
+        sources: Vec<OracleSource>,
+        quote_legs: Option<Vec<PriceSource>>,
         twap_period: u64,
     },
+    SetFeeRecipients {
+        recipients: Vec<FeeRecipient>,
+    },
+    /// send accrued collateral fee to the fee recipients
+    DistributeFees {},
+    /// set keeper bounties, paid in share or a registered collateral
+    ConfigKeeper {
+        reward_asset: AssetInfo,
+        update_oracle_reward: Uint128,
+        update_epoch_reward: Uint128,
+        refresh_collateral_ratio_reward: Uint128,
+    },
+    /// add the sent native reward asset to the keeper budget
+    FundKeeper {},
+    /// reject mint and redeem when spot moves further than this from TWAP
+    SetOracleMaxDeviation {
+        oracle: OracleKind,
+        max_deviation: Option<Uint128>,
+    },
+    /// set snapshot interval and ring buffer size of an oracle
+    SetOracleObservations {
+        oracle: OracleKind,
+        observation_interval: u64,
+        max_observations: u32,
+    },
     RefreshCollateralRatio {},
     UpdateEpoch {},
     SetEpochConfig {
         ceil_price: Option<Uint128>,
         epoch_duration: u64,
         max_expansion_rate: Option<Uint128>,
+        /// replace the tier table, both must be set together
+        supply_tiers: Option<Vec<Uint128>>,
+        expansion_rates: Option<Vec<Uint128>>,
+    },
+    /// mint each expansion to the seigniorage recipients, None to disable
+    SetSeigniorage {
+        seigniorage: Option<SeigniorageConfig>,
     },
 
     /// internal use only
     BurnShare {},
+    /// internal use only, mint the boardroom seigniorage of `epoch` and allocate it
+    AllocateSeigniorage {
+        epoch: u64,
+        amount: Uint128,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum Cw20CallbackMsg {
+    /// mint synth with the sent cw20 collateral
+    Mint {
+        min_synth_out: Uint128,
+        min_share_bought: Option<Uint128>,
+    },
+    Recollateralize {
+        min_share_out: Uint128,
+    },
+    /// sell share for excess collateral
+    Buyback {
+        /// collateral to receive, default to the primary collateral
+        collateral: Option<AssetInfo>,
+        min_collateral_out: Uint128,
+    },
     Redeem {
+        /// collateral to redeem for, default to the primary collateral
+        collateral: Option<AssetInfo>,
         min_collateral_out: Uint128,
         min_share_out: Uint128,
     },
+    /// add the sent cw20 reward asset to the keeper budget
+    FundKeeper {},
+    /// burn the sent synth for bonds while synth is below peg
+    BuyBond {
+        min_bond_out: Uint128,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -82,13 +210,178 @@ pub enum QueryMsg {
     GetUserInfo {
         address: Addr,
     },
+    /// list users in address order
+    AllUsers {
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+    },
+    /// list users with synth, share or collateral left to collect. A page can
+    /// hold fewer than `limit` users, continue from `next_start_after`
+    UsersWithUnclaimed {
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+    },
+    /// recompute pool ledgers and compare them with user ledgers and
+    /// on-chain balances, response is an `InvariantsResponse`. Users are read
+    /// by pages: pass back `next_start_after` and `user_sums` until the cursor
+    /// is None, the user ledger checks run on that last page
+    CheckInvariants {
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+        /// `user_sums` of the previous page
+        user_sums: Option<UserLedgerSums>,
+    },
     CalcMint {
         collateral_amount: Uint128,
+        collateral: Option<AssetInfo>,
     },
     CalcRedeem {
         synth_amount: Uint128,
+        collateral: Option<AssetInfo>,
     },
     GetPrice {},
+    /// get collateral value held against the one required by collateral ratio
+    GetCollateralValue {},
+    /// get fee recipients and cumulative distributed fee
+    GetFeeInfo {},
+    /// get keeper bounties and remaining budget
+    GetKeeperInfo {},
+    /// get epoch duration, ceil price and expansion tier table
+    GetEpochConfig {},
+    /// get current epoch start, next epoch time and last TWAP
+    GetEpochInfo {},
+    /// get synth still mintable before the epoch allowance is reached
+    GetMintAllowance {},
+    /// get bond totals, current bond price and redeemable allowance
+    GetBondInfo {},
+    /// get bonds held by `address`
+    GetUserBond {
+        address: String,
+    },
+    /// get completed epochs, oldest first
+    EpochHistory {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// get TWAP of an oracle over the last `window` seconds of observations
+    GetTwap {
+        oracle: OracleKind,
+        window: u64,
+    },
+    /// get past collateral ratio refreshes, oldest first
+    CollateralRatioHistory {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PokeStatus {
+    Ran,
+    Skipped { reason: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PokeStep {
+    pub step: String,
+    pub status: PokeStatus,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PokeResponse {
+    pub steps: Vec<PokeStep>,
+}
+
+impl PokeResponse {
+    /// record the outcome of `step`, return its value if it ran
+    pub fn record<T, E: ToString>(&mut self, step: &str, result: Result<T, E>) -> Option<T> {
+        let status = match &result {
+            Ok(_) => PokeStatus::Ran,
+            Err(err) => PokeStatus::Skipped {
+                reason: err.to_string(),
+            },
+        };
+        self.steps.push(PokeStep {
+            step: step.to_string(),
+            status,
+        });
+        result.ok()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EpochConfigResponse {
+    pub epoch_duration: u64,
+    pub ceil_price: Option<Uint128>,
+    pub max_expansion_rate: Option<Uint128>,
+    pub supply_tiers: Vec<Uint128>,
+    pub expansion_rates: Vec<Uint128>,
+    pub seigniorage: Option<SeigniorageConfig>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EpochInfoResponse {
+    pub epoch: u64,
+    pub start_timestamp: u64,
+    pub next_epoch_timestamp: u64,
+    pub last_twap: Option<Uint128>,
+    pub base_supply: Uint128,
+    pub max_supply: Option<Uint128>,
+    /// supply allowed at query time, None when unlimited
+    pub allowed_supply: Option<Uint128>,
+    /// expansion still reserved for bond redemptions
+    pub bond_allowance: Uint128,
+    /// split of the seigniorage minted at the current epoch start
+    pub seigniorage: Option<SeigniorageSplit>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MintAllowanceResponse {
+    pub current_supply: Uint128,
+    pub allowed_supply: Option<Uint128>,
+    /// None when unlimited
+    pub remaining: Option<Uint128>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UsersResponse {
+    pub users: Vec<UserEntry>,
+    /// where to continue listing, None once every user was read
+    pub next_start_after: Option<Addr>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BondInfoResponse {
+    pub total_outstanding: Uint128,
+    pub total_issued: Uint128,
+    pub total_redeemed: Uint128,
+    pub total_burnt: Uint128,
+    /// synth paid per bond, None while synth is not below peg
+    pub bond_price: Option<Uint128>,
+    /// bonds redeemable in the current epoch
+    pub redeemable: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UserBondResponse {
+    pub bonds: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EpochHistoryResponse {
+    pub records: Vec<EpochRecord>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TwapResponse {
+    pub twap: Uint128,
+    pub window: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CollateralRatioHistoryResponse {
+    pub records: Vec<CollateralRatioRecord>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -102,24 +395,109 @@ pub struct PoolInfoResponse {
     pub collateral_denom: String,
     pub synth: Addr,
     pub share: Addr,
+    pub router: Addr,
+    pub swap_route: Vec<AssetInfo>,
+    pub buyback_max_slippage_bps: u64,
     pub collateral_ratio: Uint128,
     pub min_collateral_ratio: Uint128,
     pub last_refresh_collateral_ratio: u64,
     pub price_band: Uint128,
     pub collateral_ratio_step: Uint128,
     pub refresh_collateral_ratio_cooldown: u64,
-    pub collateral_balance: Uint128,
+    pub ratio_controller: RatioController,
+    pub pid_state: PidState,
+    pub collaterals: Vec<CollateralInfoResponse>,
+    /// value of all collateral in peg asset, None if a price is unavailable
+    pub total_collateral_value: Option<Uint128>,
     pub minting_fee: Uint128,
     pub redemption_fee: Uint128,
-    pub total_unclaimed_collateral: Uint128,
     pub total_unclaimed_synth: Uint128,
     pub total_unclaimed_share: Uint128,
+    pub total_redeemed_synth: Uint128,
     pub oracle: OracleInfoResponse,
+    pub share_price_mode: PriceMode,
+    pub max_twap_age: u64,
+    pub recollateralize_bonus: Uint128,
+    pub buyback_fee: Uint128,
     pub mint_paused: bool,
     pub redeem_paused: bool,
+    pub recollateralize_paused: bool,
+    pub buyback_paused: bool,
     pub owner: Addr,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CollateralInfoResponse {
+    pub collateral: CollateralInfo,
+    /// amount backing the pool, excluding fee and unclaimed redemption
+    pub holding: Uint128,
+    pub price: Option<Uint128>,
+    /// oracle of a collateral priced by `CollateralPrice::Oracle`
+    pub oracle: Option<PairOracleState>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum InvariantCheck {
+    /// `total_unclaimed_synth` equals the sum of user synth balances
+    UnclaimedSynth,
+    /// `total_unclaimed_share` equals the sum of user share balances
+    UnclaimedShare,
+    /// collateral `total_unclaimed` equals the sum of user collateral balances
+    UnclaimedCollateral,
+    /// collateral held covers its fee, unclaimed and keeper reserve
+    CollateralBalance,
+    /// share held covers the keeper budget paid in share
+    KeeperShareBudget,
+    /// synth held covers the synth received by redeem
+    RedeemedSynth,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Discrepancy {
+    pub check: InvariantCheck,
+    /// collateral the check applies to, if any
+    pub asset: Option<AssetInfo>,
+    /// amount recorded by the pool ledger
+    pub expected: Uint128,
+    /// amount recomputed from user ledgers or held on-chain
+    pub actual: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InvariantsResponse {
+    /// user ledgers summed so far, including the previous pages
+    pub user_sums: UserLedgerSums,
+    /// where to continue, None once every user was read
+    pub next_start_after: Option<Addr>,
+    pub synth_supply: Uint128,
+    pub share_supply: Uint128,
+    /// synth held by the pool
+    pub synth_held: Uint128,
+    /// empty when every invariant holds
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct UserLedgerSums {
+    pub users_checked: u64,
+    pub synth_balance: Uint128,
+    pub share_balance: Uint128,
+    pub collateral_balance: Vec<Asset>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CollateralValueResponse {
+    /// synth in circulation, including minted but not collected
+    pub synth_supply: Uint128,
+    /// value of collateral held, in peg asset
+    pub global_collateral_value: Uint128,
+    /// value of collateral required at current collateral ratio
+    pub target_collateral_value: Uint128,
+    pub deficit: Uint128,
+    pub excess: Uint128,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct CalcMintResult {
     pub synth_out: Uint128,
@@ -140,4 +518,16 @@ pub struct GetPriceResult {
     pub synth_spot: Uint128,
     pub share_twap: Option<Uint128>,
     pub synth_twap: Option<Uint128>,
+    /// relative distance between spot and TWAP
+    pub share_deviation: Option<Uint128>,
+    pub synth_deviation: Option<Uint128>,
+    /// collaterals priced by an oracle, empty until one is configured
+    pub collaterals: Vec<CollateralPriceResult>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CollateralPriceResult {
+    pub asset: AssetInfo,
+    pub spot: Option<Uint128>,
+    pub twap: Option<Uint128>,
 }
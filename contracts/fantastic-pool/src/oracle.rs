@@ -1,8 +1,9 @@
-use astroport::pair::PoolResponse;
+use astroport::asset::{Asset, AssetInfo};
 use cosmwasm_std::{Addr, QuerierWrapper, StdError, StdResult, Storage, Timestamp, Uint128};
 use cw_storage_plus::Item;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 
 use crate::{
     helpers::{SwapPairUtils, Unit},
@@ -13,88 +14,621 @@ pub const SYNTH_ORACLE: PairOracle = PairOracle::new("synth_oracle");
 
 pub const SHARE_ORACLE: PairOracle = PairOracle::new("share_oracle");
 
+/// how a price is read from the oracle
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceMode {
+    /// current pair reserve, can be moved within a transaction
+    #[default]
+    Spot,
+    /// last computed TWAP
+    Twap,
+    /// the one of spot and TWAP that benefits the protocol
+    Conservative,
+}
+
+/// select one of the pool oracles
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OracleKind {
+    Synth,
+    Share,
+    /// oracle of a collateral priced by `CollateralPrice::Oracle`
+    Collateral {
+        asset: AssetInfo,
+    },
+}
+
+impl OracleKind {
+    pub fn oracle(&self) -> PairOracle {
+        match self {
+            OracleKind::Synth => SYNTH_ORACLE,
+            OracleKind::Share => SHARE_ORACLE,
+            OracleKind::Collateral { asset } => PairOracle::collateral(asset),
+        }
+    }
+}
+
+/// where an oracle reads the price of its base asset, in precision unit
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceSource {
+    /// astroport constant product pair
+    AstroportXyk { pair_addr: Addr, base_index: u8 },
+    /// astroport stableswap pair, spot is read from a swap simulation
+    AstroportStable { pair_addr: Addr, base_index: u8 },
+    /// constant price set by admin
+    Fixed { price: Uint128 },
+    /// contract answering `ExternalOracleQueryMsg::Price`, a price not updated
+    /// in the last `max_age` seconds is rejected
+    External {
+        contract_addr: Addr,
+        asset: AssetInfo,
+        max_age: u64,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExternalOracleQueryMsg {
+    Price { asset: AssetInfo },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ExternalPriceResponse {
+    /// price in precision unit
+    pub price: Uint128,
+    pub last_updated: u64,
+}
+
+/// one price source of an oracle, the oracle reports the median of usable ones
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OracleSource {
+    pub source: PriceSource,
+    /// min quote asset reserve of a pair source
+    pub min_liquidity: Uint128,
+}
+
+impl OracleSource {
+    /// reserves of a pair source, None for other sources. Fail when the pair
+    /// lacks liquidity or cannot be queried
+    fn get_reserves(&self, querier: &QuerierWrapper) -> StdResult<Option<(Asset, Asset)>> {
+        match &self.source {
+            PriceSource::AstroportXyk {
+                pair_addr,
+                base_index,
+            }
+            | PriceSource::AstroportStable {
+                pair_addr,
+                base_index,
+            } => {
+                let (base, quote) = SwapPairUtils::query_reserves(querier, pair_addr, *base_index)?;
+                if quote.amount < self.min_liquidity {
+                    return Err(StdError::generic_err("Pair liquidity below minimum"));
+                }
+                Ok(Some((base, quote)))
+            }
+            PriceSource::Fixed { .. } | PriceSource::External { .. } => Ok(None),
+        }
+    }
+
+    /// None when the source lacks liquidity, reports a stale price or cannot be queried
+    pub fn get_spot_price(&self, querier: &QuerierWrapper, now: u64) -> Option<Uint128> {
+        match self.get_reserves(querier).ok()? {
+            Some((base, quote)) => self.source.get_pair_price(querier, base, quote),
+            None => self.source.get_spot_price(querier, now),
+        }
+        .ok()
+    }
+
+    pub fn get_cumulative_price(
+        &self,
+        querier: &QuerierWrapper,
+        last_cumulative: Uint128,
+        elapsed: u64,
+        now: u64,
+    ) -> Option<Uint128> {
+        self.get_reserves(querier).ok()?;
+        self.source
+            .get_cumulative_price(querier, last_cumulative, elapsed, now)
+            .ok()
+    }
+}
+
+/// check a list of oracle sources set by admin
+pub fn validate_sources(sources: &[OracleSource]) -> StdResult<()> {
+    if sources.is_empty() {
+        return Err(StdError::generic_err(
+            "At least one price source is required",
+        ));
+    }
+    for OracleSource { source, .. } in sources {
+        source.validate()?;
+    }
+    Ok(())
+}
+
+/// median of `values`, None if empty
+pub fn median(mut values: Vec<Uint128>) -> Option<Uint128> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort();
+    let mid = values.len() / 2;
+    if values.len() % 2 == 1 {
+        Some(values[mid])
+    } else {
+        Some((values[mid - 1] + values[mid]) / Uint128::from(2u8))
+    }
+}
+
+/// median spot price of the usable sources
+pub fn get_median_spot_price(
+    querier: &QuerierWrapper,
+    sources: &[OracleSource],
+    now: u64,
+) -> Option<Uint128> {
+    median(
+        sources
+            .iter()
+            .filter_map(|x| x.get_spot_price(querier, now))
+            .collect(),
+    )
+}
+
+/// cumulative price of each source continuing from `last`, None for unusable sources
+pub fn get_source_cumulatives(
+    querier: &QuerierWrapper,
+    sources: &[OracleSource],
+    last: &[Uint128],
+    elapsed: u64,
+    now: u64,
+) -> Vec<Option<Uint128>> {
+    sources
+        .iter()
+        .enumerate()
+        .map(|(i, source)| {
+            let last = last.get(i).copied().unwrap_or_default();
+            source.get_cumulative_price(querier, last, elapsed, now)
+        })
+        .collect()
+}
+
+impl PriceSource {
+    pub fn validate(&self) -> StdResult<()> {
+        match self {
+            PriceSource::AstroportXyk { base_index, .. }
+            | PriceSource::AstroportStable { base_index, .. }
+                if *base_index > 1 =>
+            {
+                Err(StdError::generic_err("Token index should be 0 or 1"))
+            }
+            PriceSource::Fixed { price } if price.is_zero() => {
+                Err(StdError::generic_err("Fixed price cannot be zero"))
+            }
+            PriceSource::External { max_age, .. } if *max_age == 0 => Err(StdError::generic_err(
+                "External price max age cannot be zero",
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn get_spot_price(&self, querier: &QuerierWrapper, now: u64) -> StdResult<Uint128> {
+        let price = match self {
+            PriceSource::AstroportXyk {
+                pair_addr,
+                base_index,
+            }
+            | PriceSource::AstroportStable {
+                pair_addr,
+                base_index,
+            } => {
+                let (base, quote) = SwapPairUtils::query_reserves(querier, pair_addr, *base_index)?;
+                self.get_pair_price(querier, base, quote)?
+            }
+            PriceSource::Fixed { price } => *price,
+            PriceSource::External {
+                contract_addr,
+                asset,
+                max_age,
+            } => {
+                let ExternalPriceResponse {
+                    price,
+                    last_updated,
+                } = querier.query_wasm_smart(
+                    contract_addr,
+                    &ExternalOracleQueryMsg::Price {
+                        asset: asset.clone(),
+                    },
+                )?;
+                if last_updated + max_age < now {
+                    return Err(StdError::generic_err("External price is outdated"));
+                }
+                price
+            }
+        };
+        Ok(price)
+    }
+
+    /// spot price of a pair source from its reserves
+    fn get_pair_price(
+        &self,
+        querier: &QuerierWrapper,
+        base: Asset,
+        quote: Asset,
+    ) -> StdResult<Uint128> {
+        match self {
+            PriceSource::AstroportXyk { .. } => {
+                if base.amount.is_zero() {
+                    return Err(StdError::generic_err("Pair has no liquidity"));
+                }
+                Ok(quote.amount * Unit::precision() / base.amount)
+            }
+            PriceSource::AstroportStable { pair_addr, .. } => SwapPairUtils::query_simulation(
+                querier,
+                pair_addr,
+                Asset {
+                    info: base.info,
+                    amount: Unit::precision(),
+                },
+            ),
+            PriceSource::Fixed { .. } | PriceSource::External { .. } => {
+                Err(StdError::generic_err("Not a pair source"))
+            }
+        }
+    }
+
+    /// cumulative price to compute TWAP from. Sources without an on-chain
+    /// accumulator extend `last_cumulative` with their current price, so the
+    /// resulting TWAP is the price at update time
+    pub fn get_cumulative_price(
+        &self,
+        querier: &QuerierWrapper,
+        last_cumulative: Uint128,
+        elapsed: u64,
+        now: u64,
+    ) -> StdResult<Uint128> {
+        match self {
+            PriceSource::AstroportXyk {
+                pair_addr,
+                base_index,
+            }
+            | PriceSource::AstroportStable {
+                pair_addr,
+                base_index,
+            } => SwapPairUtils::query_cumulative_prices(querier, pair_addr, *base_index),
+            PriceSource::Fixed { .. } | PriceSource::External { .. } => {
+                let price = self.get_spot_price(querier, now)?;
+                Ok(last_cumulative + price * Uint128::from(elapsed))
+            }
+        }
+    }
+}
+
+/// cumulative prices of each quote leg, continuing from `last`
+pub fn get_leg_cumulatives(
+    querier: &QuerierWrapper,
+    legs: &[PriceSource],
+    last: &[Uint128],
+    elapsed: u64,
+    now: u64,
+) -> StdResult<Vec<Uint128>> {
+    legs.iter()
+        .enumerate()
+        .map(|(i, leg)| {
+            leg.get_cumulative_price(
+                querier,
+                last.get(i).copied().unwrap_or_default(),
+                elapsed,
+                now,
+            )
+        })
+        .collect()
+}
+
+/// convert a price through each leg, eg: share/LUNA × LUNA/USDC
+pub fn chain_price(price: Uint128, leg_prices: impl IntoIterator<Item = Uint128>) -> Uint128 {
+    leg_prices
+        .into_iter()
+        .fold(price, |acc, x| acc * x / Unit::precision())
+}
+
+/// combined TWAP of a price chain between two sets of cumulative prices:
+/// median TWAP of the sources usable at both ends, through each quote leg.
+/// None when no source is usable, an error when a leg goes backward or no time
+/// elapsed
+pub fn chain_twap(
+    start: (&[Option<Uint128>], &[Uint128]),
+    end: (&[Option<Uint128>], &[Uint128]),
+    elapsed: u64,
+) -> StdResult<Option<Uint128>> {
+    let elapsed = Uint128::from(elapsed);
+    let mut twaps = vec![];
+    for (start, end) in start.0.iter().zip(end.0.iter()) {
+        if let (Some(start), Some(end)) = (start, end) {
+            // a source whose cumulative price reset is left out
+            if let Ok(diff) = end.checked_sub(*start) {
+                twaps.push(diff.checked_div(elapsed)?);
+            }
+        }
+    }
+    let leg_twaps = end
+        .1
+        .iter()
+        .zip(start.1.iter())
+        .map(|(end, start)| Ok(end.checked_sub(*start)?.checked_div(elapsed)?))
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(median(twaps).map(|twap| chain_price(twap, leg_twaps)))
+}
+
+/// cumulative price snapshot
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Observation {
+    pub timestamp: u64,
+    /// per source, None if the source was unusable
+    pub price_cumulatives: Vec<Option<Uint128>>,
+    pub quote_cumulatives: Vec<Uint128>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct PairOracleState {
-    pub pair_addr: Addr,
-    pub base_index: u8,
-    pub price_cumulative_last: Uint128,
+    pub sources: Vec<OracleSource>,
+    /// last known cumulative price of each source
+    pub price_cumulatives_last: Vec<Uint128>,
+    /// sources converting the quote asset of `sources` into the reference
+    /// currency, applied in order
+    pub quote_legs: Vec<PriceSource>,
+    pub quote_cumulatives_last: Vec<Uint128>,
+    /// TWAP over the last `twap_period` seconds, through all quote legs
     pub twap: Option<Uint128>,
+    /// time `twap` was last computed
+    pub twap_timestamp: u64,
+    /// time of the last snapshot
     pub last_update: u64,
     pub twap_period: u64,
+    /// max distance allowed between spot and TWAP, None to disable the guard
+    pub max_deviation: Option<Uint128>,
+
+    /// ring buffer of snapshots, `observation_index` is the slot written next
+    pub observations: Vec<Observation>,
+    pub observation_index: u32,
+    pub max_observations: u32,
+    /// min seconds between two snapshots
+    pub observation_interval: u64,
 }
 
 impl Default for PairOracleState {
     fn default() -> Self {
         Self {
-            pair_addr: Addr::unchecked(""),
-            base_index: 0,
-            price_cumulative_last: Uint128::zero(),
+            sources: vec![],
+            price_cumulatives_last: vec![],
+            quote_legs: vec![],
+            quote_cumulatives_last: vec![],
             twap: None,
+            twap_timestamp: 0,
             last_update: 0,
             twap_period: 600,
+            max_deviation: None,
+            observations: vec![],
+            observation_index: 0,
+            max_observations: 36,
+            observation_interval: 100,
         }
     }
 }
 
 impl PairOracleState {
-    pub fn get_spot_price(&self, querier: &QuerierWrapper) -> Result<Uint128, ContractError> {
-        let (base_reserve, quote_reserve) =
-            PairOracleState::get_pair_reserve(querier, &self.pair_addr, self.base_index.into())?;
+    pub fn get_spot_price(
+        &self,
+        querier: &QuerierWrapper,
+        now: u64,
+    ) -> Result<Uint128, ContractError> {
+        let price = get_median_spot_price(querier, &self.sources, now)
+            .ok_or(ContractError::PriceUnavailableOrOutdated {})?;
+        let leg_prices = self
+            .quote_legs
+            .iter()
+            .map(|leg| leg.get_spot_price(querier, now))
+            .collect::<StdResult<Vec<_>>>()?;
+        Ok(chain_price(price, leg_prices))
+    }
+
+    /// store a snapshot, overwriting the oldest one when the buffer is full
+    fn push_observation(&mut self, observation: Observation) {
+        let capacity = self.max_observations.max(1) as usize;
+        let index = self.observation_index as usize;
+        if index < self.observations.len() {
+            self.observations[index] = observation;
+        } else {
+            self.observations.push(observation);
+        }
+        self.observation_index = ((index + 1) % capacity) as u32;
+    }
+
+    /// TWAP from the newest snapshot back to the latest one taken at least
+    /// `window` seconds before it. A source unusable at any snapshot of the
+    /// window is left out, its cumulative price does not cover the gap
+    pub fn consult(&self, window: u64) -> Result<Uint128, ContractError> {
+        let latest = self
+            .observations
+            .iter()
+            .max_by_key(|x| x.timestamp)
+            .ok_or(ContractError::PriceUnavailableOrOutdated {})?;
+        let start = self
+            .observations
+            .iter()
+            .filter(|x| x.timestamp + window <= latest.timestamp)
+            .max_by_key(|x| x.timestamp)
+            .ok_or(ContractError::TwapWindowNotCovered { window })?;
+        if start.timestamp == latest.timestamp {
+            return Err(ContractError::TwapWindowNotCovered { window });
+        }
+        let start_cumulatives: Vec<Option<Uint128>> = start
+            .price_cumulatives
+            .iter()
+            .enumerate()
+            .map(|(i, cumulative)| {
+                let gap = self
+                    .observations
+                    .iter()
+                    .filter(|x| x.timestamp > start.timestamp && x.timestamp < latest.timestamp)
+                    .any(|x| x.price_cumulatives.get(i).copied().flatten().is_none());
+                cumulative.filter(|_| !gap)
+            })
+            .collect();
 
-        Ok(quote_reserve * Unit::precision() / base_reserve)
+        chain_twap(
+            (&start_cumulatives, &start.quote_cumulatives),
+            (&latest.price_cumulatives, &latest.quote_cumulatives),
+            latest.timestamp - start.timestamp,
+        )?
+        .ok_or(ContractError::PriceUnavailableOrOutdated {})
+    }
+
+    /// relative distance between spot and TWAP
+    pub fn calc_deviation(spot: Uint128, twap: Uint128) -> Uint128 {
+        let diff = if spot > twap {
+            spot - twap
+        } else {
+            twap - spot
+        };
+        diff.multiply_ratio(Unit::precision(), twap)
     }
 
-    fn get_pair_reserve(
+    /// fail when the spot price moved away from TWAP more than allowed
+    pub fn assert_deviation(
+        &self,
         querier: &QuerierWrapper,
-        pair_addr: &Addr,
-        base_index: usize,
-    ) -> Result<(Uint128, Uint128), ContractError> {
-        let pair_info: PoolResponse =
-            querier.query_wasm_smart(pair_addr, &astroport::pair::QueryMsg::Pool {})?;
-
-        let quote_index = base_index + 1 % 2; // since assets length alway be 2
-        let base_reserve = pair_info
-            .assets
-            .get(base_index)
-            .ok_or(ContractError::Std(StdError::generic_err(
-                "invalid pool info",
-            )))?
-            .amount;
-        let quote_reserve = pair_info
-            .assets
-            .get(quote_index)
-            .ok_or(ContractError::Std(StdError::generic_err(
-                "invalid pool info",
-            )))?
-            .amount;
-        Ok((base_reserve, quote_reserve))
-    }
-}
-
-pub struct PairOracle<'a>(Item<'a, PairOracleState>);
-
-impl<'a> PairOracle<'a> {
-    pub const fn new(storage_key: &'a str) -> Self {
-        PairOracle(Item::new(storage_key))
+        now: u64,
+    ) -> Result<(), ContractError> {
+        if let Some(max_deviation) = self.max_deviation {
+            let twap = self
+                .twap
+                .filter(|x| !x.is_zero())
+                .ok_or(ContractError::PriceUnavailableOrOutdated {})?;
+            let spot = self.get_spot_price(querier, now)?;
+            if PairOracleState::calc_deviation(spot, twap) > max_deviation {
+                return Err(ContractError::PriceDeviationTooLarge { spot, twap });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// the ring buffer must span `twap_period`, the oldest snapshot is
+/// `(max_observations - 1) * observation_interval` seconds before the newest
+fn assert_twap_covered(
+    twap_period: u64,
+    observation_interval: u64,
+    max_observations: u32,
+) -> StdResult<()> {
+    if (max_observations.saturating_sub(1) as u64) * observation_interval < twap_period {
+        return Err(StdError::generic_err(
+            "Observations cannot cover the TWAP period",
+        ));
+    }
+    Ok(())
+}
+
+/// oracle state stored under its own key
+pub struct PairOracle(Cow<'static, str>);
+
+impl PairOracle {
+    pub const fn new(storage_key: &'static str) -> Self {
+        PairOracle(Cow::Borrowed(storage_key))
+    }
+
+    /// oracle pricing `asset`, each collateral keeps its own TWAP
+    pub fn collateral(asset: &AssetInfo) -> Self {
+        PairOracle(Cow::Owned(format!("collateral_oracle_{}", asset)))
+    }
+
+    fn state(&self) -> Item<'_, PairOracleState> {
+        Item::new(&self.0)
     }
 
     pub fn initialize(&self, storage: &mut dyn Storage) -> StdResult<()> {
-        self.0.save(storage, &PairOracleState::default())
+        self.state().save(storage, &PairOracleState::default())
     }
 
+    /// last TWAP and the time it was computed
     pub fn get_twap(&self, storage: &dyn Storage) -> Result<(Uint128, u64), ContractError> {
         let PairOracleState {
-            twap, last_update, ..
-        } = self.0.load(storage)?;
+            twap,
+            twap_timestamp,
+            ..
+        } = self.state().load(storage)?;
         if twap.is_none() {
             return Err(ContractError::PriceUnavailableOrOutdated {});
         }
 
-        Ok((twap.unwrap(), last_update))
+        Ok((twap.unwrap(), twap_timestamp))
+    }
+
+    /// TWAP computed no longer than `max_age` seconds ago
+    pub fn get_fresh_twap(
+        &self,
+        storage: &dyn Storage,
+        now: u64,
+        max_age: u64,
+    ) -> Result<Uint128, ContractError> {
+        let (twap, twap_timestamp) = self.get_twap(storage)?;
+        if twap_timestamp + max_age < now {
+            return Err(ContractError::PriceUnavailableOrOutdated {});
+        }
+        Ok(twap)
+    }
+
+    /// read the price following `mode`. `pays_out` is true when the protocol gives
+    /// the base asset away, so the higher price benefits the protocol
+    pub fn get_price(
+        &self,
+        storage: &dyn Storage,
+        querier: &QuerierWrapper,
+        mode: &PriceMode,
+        pays_out: bool,
+        now: u64,
+        max_twap_age: u64,
+    ) -> Result<Uint128, ContractError> {
+        match mode {
+            PriceMode::Spot => self.get_spot_price(storage, querier, now),
+            PriceMode::Twap => self.get_fresh_twap(storage, now, max_twap_age),
+            PriceMode::Conservative => {
+                let spot = self.get_spot_price(storage, querier, now)?;
+                let twap = self.get_fresh_twap(storage, now, max_twap_age)?;
+                if pays_out {
+                    Ok(spot.max(twap))
+                } else {
+                    Ok(spot.min(twap))
+                }
+            }
+        }
+    }
+
+    pub fn assert_deviation(
+        &self,
+        storage: &dyn Storage,
+        querier: &QuerierWrapper,
+        now: u64,
+    ) -> Result<(), ContractError> {
+        self.state().load(storage)?.assert_deviation(querier, now)
+    }
+
+    /// set max deviation between spot and TWAP. Allow from admin only
+    pub fn set_max_deviation(
+        &self,
+        storage: &mut dyn Storage,
+        max_deviation: Option<Uint128>,
+    ) -> Result<(), ContractError> {
+        self.state().update(storage, |mut state| -> StdResult<_> {
+            state.max_deviation = max_deviation;
+            Ok(state)
+        })?;
+        Ok(())
     }
 
     pub fn get_state(&self, storage: &dyn Storage) -> Result<PairOracleState, ContractError> {
-        let state = self.0.load(storage)?;
+        let state = self.state().load(storage)?;
         Ok(state)
     }
 
@@ -102,35 +636,97 @@ impl<'a> PairOracle<'a> {
         &self,
         storage: &dyn Storage,
         querier: &QuerierWrapper,
+        now: u64,
     ) -> Result<Uint128, ContractError> {
-        let state = self.0.load(storage)?;
-        state.get_spot_price(querier)
+        let state = self.state().load(storage)?;
+        state.get_spot_price(querier, now)
+    }
+
+    /// TWAP over the last `window` seconds of snapshots
+    pub fn get_window_twap(
+        &self,
+        storage: &dyn Storage,
+        window: u64,
+    ) -> Result<Uint128, ContractError> {
+        self.state().load(storage)?.consult(window)
+    }
+
+    /// set how often snapshots are taken and how many are kept. Allow from admin only
+    pub fn set_observations(
+        &self,
+        storage: &mut dyn Storage,
+        observation_interval: u64,
+        max_observations: u32,
+    ) -> Result<(), ContractError> {
+        if max_observations < 2 || observation_interval == 0 {
+            return Err(ContractError::Std(StdError::generic_err(
+                "Need at least 2 observations and a non-zero interval",
+            )));
+        }
+        self.state().update(storage, |mut state| -> StdResult<_> {
+            assert_twap_covered(state.twap_period, observation_interval, max_observations)?;
+            // keep the newest snapshots, ordered so the next write overwrites the oldest
+            let mut observations = state.observations.clone();
+            observations.sort_by_key(|x| x.timestamp);
+            let skip = observations.len().saturating_sub(max_observations as usize);
+            state.observations = observations.split_off(skip);
+            state.observation_index = state.observations.len() as u32 % max_observations;
+            state.max_observations = max_observations;
+            state.observation_interval = observation_interval;
+            Ok(state)
+        })?;
+        Ok(())
     }
 
-    /// recalculate TWAP when period elapsed
+    /// take a snapshot when the observation interval elapsed and refresh TWAP
     pub fn update_twap(
         &self,
         storage: &mut dyn Storage,
         querier: &QuerierWrapper,
         now: u64,
     ) -> Result<PairOracleState, ContractError> {
-        let mut state = self.0.load(storage)?;
-        let next_update = state.last_update + state.twap_period;
+        let mut state = self.state().load(storage)?;
+        let next_update = state.last_update + state.observation_interval;
         if next_update > now {
             return Err(ContractError::TwapPeriodNotElapsed {
                 time: Timestamp::from_seconds(next_update),
             });
         }
-        let cumulative_price =
-            SwapPairUtils::query_cumulative_prices(&querier, &state.pair_addr, state.base_index)?;
-        state.twap = Some(
-            (cumulative_price - state.price_cumulative_last)
-                / Uint128::from(now - state.last_update),
+        let price_cumulatives = get_source_cumulatives(
+            querier,
+            &state.sources,
+            &state.price_cumulatives_last,
+            now - state.last_update,
+            now,
         );
-        state.price_cumulative_last = cumulative_price;
+        if price_cumulatives.iter().all(|x| x.is_none()) {
+            return Err(ContractError::PriceUnavailableOrOutdated {});
+        }
+        let quote_cumulatives = get_leg_cumulatives(
+            querier,
+            &state.quote_legs,
+            &state.quote_cumulatives_last,
+            now - state.last_update,
+            now,
+        )?;
+        state.price_cumulatives_last = price_cumulatives
+            .iter()
+            .zip(state.price_cumulatives_last.iter())
+            .map(|(new, last)| new.unwrap_or(*last))
+            .collect();
+        state.push_observation(Observation {
+            timestamp: now,
+            price_cumulatives,
+            quote_cumulatives: quote_cumulatives.clone(),
+        });
+        state.quote_cumulatives_last = quote_cumulatives;
         state.last_update = now;
+        if let Ok(twap) = state.consult(state.twap_period) {
+            state.twap = Some(twap);
+            state.twap_timestamp = now;
+        }
 
-        self.0.save(storage, &state)?;
+        self.state().save(storage, &state)?;
         Ok(state)
     }
 
@@ -139,42 +735,190 @@ impl<'a> PairOracle<'a> {
         &self,
         storage: &mut dyn Storage,
         querier: &QuerierWrapper,
-        pair_addr: &Addr,
-        base_index: u8,
+        sources: &[OracleSource],
+        quote_legs: &[PriceSource],
         twap_period: u64,
         now: u64,
     ) -> Result<(), ContractError> {
-        if base_index > 1 {
-            return Err(ContractError::Std(StdError::generic_err(
-                "Token index should be 0 or 1",
-            )));
+        validate_sources(sources)?;
+        for leg in quote_legs {
+            leg.validate()?;
         }
-        let oracle = self.0.may_load(storage)?;
+        let oracle = self.state().may_load(storage)?;
+        let (observation_interval, max_observations) = oracle
+            .as_ref()
+            .map(|x| (x.observation_interval, x.max_observations))
+            .unwrap_or_else(|| {
+                let default = PairOracleState::default();
+                (default.observation_interval, default.max_observations)
+            });
+        assert_twap_covered(twap_period, observation_interval, max_observations)?;
 
-        let cumulative_price =
-            SwapPairUtils::query_cumulative_prices(querier, &pair_addr, base_index)?;
-        let state = if oracle.is_some() {
+        let price_cumulatives = get_source_cumulatives(querier, sources, &[], 0, now);
+        if price_cumulatives.iter().all(|x| x.is_none()) {
+            return Err(ContractError::PriceUnavailableOrOutdated {});
+        }
+        let price_cumulatives_last: Vec<Uint128> = price_cumulatives
+            .iter()
+            .map(|x| x.unwrap_or_default())
+            .collect();
+        let quote_cumulatives = get_leg_cumulatives(querier, quote_legs, &[], 0, now)?;
+        let mut state = if oracle.is_some() {
             let mut oracle = oracle.unwrap();
-            oracle.pair_addr = pair_addr.clone();
-            oracle.base_index = base_index;
+            oracle.sources = sources.to_vec();
+            oracle.quote_legs = quote_legs.to_vec();
             oracle.last_update = now;
-            oracle.price_cumulative_last = cumulative_price;
+            oracle.price_cumulatives_last = price_cumulatives_last;
+            oracle.quote_cumulatives_last = quote_cumulatives.clone();
             oracle.twap = None;
+            oracle.twap_timestamp = 0;
             oracle.twap_period = twap_period;
+            oracle.observations = vec![];
+            oracle.observation_index = 0;
             oracle
         } else {
             PairOracleState {
-                pair_addr: pair_addr.clone(),
-                base_index,
+                sources: sources.to_vec(),
+                quote_legs: quote_legs.to_vec(),
                 last_update: now,
-                price_cumulative_last: cumulative_price,
+                price_cumulatives_last,
+                quote_cumulatives_last: quote_cumulatives.clone(),
                 twap: None,
                 twap_period,
+                ..Default::default()
             }
         };
+        state.push_observation(Observation {
+            timestamp: now,
+            price_cumulatives,
+            quote_cumulatives,
+        });
 
-        self.0.save(storage, &state)?;
+        self.state().save(storage, &state)?;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn observation(timestamp: u64, price_cumulatives: Vec<Option<u128>>) -> Observation {
+        Observation {
+            timestamp,
+            price_cumulatives: price_cumulatives
+                .into_iter()
+                .map(|x| x.map(Uint128::new))
+                .collect(),
+            quote_cumulatives: vec![],
+        }
+    }
+
+    #[test]
+    fn median_of_values() {
+        assert_eq!(median(vec![]), None);
+        assert_eq!(median(vec![Uint128::new(7)]), Some(Uint128::new(7)));
+        assert_eq!(
+            median(vec![Uint128::new(9), Uint128::new(1), Uint128::new(5)]),
+            Some(Uint128::new(5))
+        );
+        assert_eq!(
+            median(vec![
+                Uint128::new(8),
+                Uint128::new(2),
+                Uint128::new(4),
+                Uint128::new(100),
+            ]),
+            Some(Uint128::new(6))
+        );
+    }
+
+    #[test]
+    fn chain_twap_through_legs() {
+        let start = [Some(Uint128::new(1_000_000)), None, Some(Uint128::zero())];
+        let end = [
+            Some(Uint128::new(201_000_000)),
+            Some(Uint128::new(500_000_000)),
+            Some(Uint128::new(300_000_000)),
+        ];
+        // sources at 2.0 and 3.0, the one missing at start is ignored
+        let twap = chain_twap(
+            (&start, &[Uint128::zero()]),
+            (&end, &[Uint128::new(50_000_000)]),
+            100,
+        );
+        // median 2.5 through a 0.5 leg
+        assert_eq!(twap.unwrap(), Some(Uint128::new(1_250_000)));
+
+        assert_eq!(
+            chain_twap((&[None], &[]), (&end[..1], &[]), 100).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn chain_twap_rejects_leg_reset_and_empty_window() {
+        let start = [Some(Uint128::new(1_000_000))];
+        let end = [Some(Uint128::new(201_000_000))];
+        // a leg cumulative going backward
+        assert!(chain_twap(
+            (&start, &[Uint128::new(50_000_000)]),
+            (&end, &[Uint128::new(10_000_000)]),
+            100,
+        )
+        .is_err());
+        // nothing elapsed
+        assert!(chain_twap((&start, &[]), (&end, &[]), 0).is_err());
+        assert!(chain_twap(
+            (&start, &[Uint128::zero()]),
+            (&end, &[Uint128::new(50_000_000)]),
+            0,
+        )
+        .is_err());
+        // a source going backward is only left out
+        assert_eq!(chain_twap((&end, &[]), (&start, &[]), 100).unwrap(), None);
+    }
+
+    #[test]
+    fn consult_drops_source_with_gap() {
+        // a pair at 1.0 and a fixed source at 3.0 unusable at the second snapshot,
+        // its synthetic cumulative only accrued the last interval
+        let state = PairOracleState {
+            observations: vec![
+                observation(0, vec![Some(0), Some(0)]),
+                observation(100, vec![Some(100_000_000), None]),
+                observation(200, vec![Some(200_000_000), Some(300_000_000)]),
+                observation(300, vec![Some(300_000_000), Some(600_000_000)]),
+            ],
+            observation_index: 4,
+            ..Default::default()
+        };
+        assert_eq!(state.consult(300).unwrap(), Uint128::new(1_000_000));
+        assert_eq!(state.consult(200).unwrap(), Uint128::new(1_000_000));
+        // both sources usable over the whole window
+        assert_eq!(state.consult(100).unwrap(), Uint128::new(2_000_000));
+    }
+
+    #[test]
+    fn consult_needs_covered_window() {
+        let state = PairOracleState {
+            observations: vec![
+                observation(0, vec![Some(0)]),
+                observation(100, vec![Some(100)]),
+            ],
+            ..Default::default()
+        };
+        assert!(matches!(
+            state.consult(200),
+            Err(ContractError::TwapWindowNotCovered { window: 200 })
+        ));
+    }
+
+    #[test]
+    fn twap_covered_by_observations() {
+        assert!(assert_twap_covered(600, 100, 7).is_ok());
+        assert!(assert_twap_covered(600, 100, 6).is_err());
+        assert!(assert_twap_covered(600, 1_000, 1).is_err());
+    }
+}
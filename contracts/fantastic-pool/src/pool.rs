@@ -1,30 +1,47 @@
-use astroport::asset::AssetInfo;
-use astroport::router::{ExecuteMsg as AstroportRouterExecuteMsg, SwapOperation};
+use astroport::asset::{Asset, AssetInfo};
+use astroport::router::{
+    Cw20HookMsg as AstroportRouterCw20HookMsg, ExecuteMsg as AstroportRouterExecuteMsg,
+    SwapOperation,
+};
 use cosmwasm_std::{
-    to_binary, Addr, BankMsg, Coin, CosmosMsg, Env, QuerierWrapper, Response, StdResult, Storage,
-    Uint128, WasmMsg,
+    to_binary, Addr, Coin, CosmosMsg, Env, Event, Order, QuerierWrapper, Response, StdError,
+    StdResult, Storage, Uint128, WasmMsg,
 };
 use cw20::{Cw20CoinVerified, Cw20ExecuteMsg};
-use cw_storage_plus::{Item, Map};
+use cw_storage_plus::{Bound, Item, Map, U64Key};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::convert::TryInto;
 
+use crate::collateral::{add_asset, transfer_asset, CollateralInfo, COLLATERALS};
+use crate::controller::{apply_step, PidState, RatioController};
 use crate::epoch::EPOCH;
-use crate::helpers::Unit;
-use crate::msg::{CalcRedeemResult, ExecuteMsg};
-use crate::oracle::{SHARE_ORACLE, SYNTH_ORACLE};
+use crate::helpers::{TokenUtils, Unit};
+use crate::keeper::KEEPER;
+use crate::msg::{
+    CalcRedeemResult, CollateralValueResponse, Discrepancy, ExecuteMsg, InvariantCheck,
+    InvariantsResponse, UserLedgerSums, UsersResponse,
+};
+use crate::oracle::{PriceMode, SHARE_ORACLE, SYNTH_ORACLE};
 use crate::{msg::CalcMintResult, ContractError};
 
-const ASTROPORT_ROUTER: &str = "terra13wf295fj9u209nknz2cgqmmna7ry3d3j5kv7t4";
-
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct PoolConfig {
-    /// denom of collateral token, eg: uluna
+    /// denom of the primary collateral token, eg: uluna.
+    /// Other collaterals live in the collateral registry
     pub collateral_denom: String,
     /// address of the synth token
     pub synth: Addr,
     pub share: Addr,
 
+    /// astroport router used to buy back share when minting
+    pub router: Addr,
+    /// intermediate assets to hop through when swapping collateral to share,
+    /// empty means a direct collateral/share swap
+    pub swap_route: Vec<AssetInfo>,
+    /// max slippage of the share buyback against share TWAP, in basis points
+    pub buyback_max_slippage_bps: u64,
+
     /// part of synth collaterized, used for minting/redeeming
     pub collateral_ratio: Uint128,
     pub min_collateral_ratio: Uint128,
@@ -32,18 +49,33 @@ pub struct PoolConfig {
     pub refresh_collateral_ratio_cooldown: u64,
     pub collateral_ratio_step: Uint128,
     pub price_band: Uint128,
+    pub ratio_controller: RatioController,
+    pub pid_state: PidState,
 
-    /// fee charged in collateral
+    /// fee charged in collateral, accumulated per collateral in the registry
     pub minting_fee: Uint128,
     pub redemption_fee: Uint128,
 
-    pub total_fee: Uint128,
     pub total_unclaimed_synth: Uint128,
-    pub total_unclaimed_collateral: Uint128,
     pub total_unclaimed_share: Uint128,
+    /// synth received by redeem, held by the pool and never burnt
+    #[serde(default)]
+    pub total_redeemed_synth: Uint128,
+
+    /// how share is priced when the pool pays or takes share for collateral
+    pub share_price_mode: PriceMode,
+    /// TWAP older than this is rejected, in seconds
+    pub max_twap_age: u64,
+
+    /// bonus paid in share when recollateralizing
+    pub recollateralize_bonus: Uint128,
+    /// part of the share value kept as collateral when buying back share
+    pub buyback_fee: Uint128,
 
     pub mint_paused: bool,
     pub redeem_paused: bool,
+    pub recollateralize_paused: bool,
+    pub buyback_paused: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
@@ -52,43 +84,112 @@ pub struct UserInfo {
     pub last_action_block: u64,
     pub synth_balance: Uint128,
     pub share_balance: Uint128,
-    pub collateral_balance: Uint128,
+    pub collateral_balance: Vec<Asset>,
+}
+
+impl UserInfo {
+    /// anything left to `Collect`
+    pub fn has_unclaimed(&self) -> bool {
+        !self.synth_balance.is_zero()
+            || !self.share_balance.is_zero()
+            || self.collateral_balance.iter().any(|x| !x.amount.is_zero())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UserEntry {
+    pub address: Addr,
+    pub info: UserInfo,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct CollectResult {
-    pub collateral_amount: Uint128,
+    pub collateral_amount: Vec<Asset>,
     pub synth_amount: Uint128,
     pub share_amount: Uint128,
 }
 
 impl PoolConfig {
-    pub fn init(collateral_denom: String) -> Self {
+    pub fn init(collateral_denom: String, router: Addr, swap_route: Vec<AssetInfo>) -> Self {
         PoolConfig {
             collateral_denom,
             synth: Addr::unchecked(""),
             share: Addr::unchecked(""),
+            router,
+            swap_route,
+            buyback_max_slippage_bps: 100,
             collateral_ratio: Unit::precision(),
             min_collateral_ratio: Unit::precision(),
             last_refresh_collateral_ratio: 0,
             refresh_collateral_ratio_cooldown: 600,
             collateral_ratio_step: 2500u128.into(),
             price_band: 5000u128.into(),
+            ratio_controller: RatioController::default(),
+            pid_state: PidState::default(),
             minting_fee: Uint128::from(3000u128),
             redemption_fee: Uint128::from(5000u128),
-            total_fee: Uint128::zero(),
             total_unclaimed_synth: Uint128::zero(),
-            total_unclaimed_collateral: Uint128::zero(),
             total_unclaimed_share: Uint128::zero(),
+            total_redeemed_synth: Uint128::zero(),
+            share_price_mode: PriceMode::default(),
+            max_twap_age: 3600,
+            recollateralize_bonus: Uint128::from(7500u128),
+            buyback_fee: Uint128::from(3000u128),
             mint_paused: false,
             redeem_paused: false,
+            recollateralize_paused: false,
+            buyback_paused: false,
         }
     }
 
-    pub fn calc_mint(&self, collateral_amount: Uint128) -> CalcMintResult {
+    /// build the router operations swapping collateral to share through the
+    /// collateral own route, or `swap_route` if it has none
+    pub fn swap_operations(&self, collateral: &CollateralInfo) -> Vec<SwapOperation> {
+        let ask = AssetInfo::Token {
+            contract_addr: self.share.clone(),
+        };
+        let route = collateral.swap_route.as_ref().unwrap_or(&self.swap_route);
+        let assets: Vec<AssetInfo> = std::iter::once(collateral.asset.clone())
+            .chain(route.iter().cloned())
+            .chain(std::iter::once(ask))
+            .collect();
+
+        assets
+            .windows(2)
+            .map(|pair| SwapOperation::AstroSwap {
+                offer_asset_info: pair[0].clone(),
+                ask_asset_info: pair[1].clone(),
+            })
+            .collect()
+    }
+
+    /// minimum share expected when buying back `buy_share_value` of collateral,
+    /// priced by share TWAP and discounted by the allowed slippage
+    pub fn calc_min_share_bought(
+        &self,
+        buy_share_value: Uint128,
+        share_twap: Uint128,
+    ) -> Result<Uint128, ContractError> {
+        if share_twap.is_zero() {
+            return Err(ContractError::PriceUnavailableOrOutdated {});
+        }
+        let expected = buy_share_value * Unit::precision() / share_twap;
+        Ok(expected.multiply_ratio(
+            Unit::BPS_PRECISION - self.buyback_max_slippage_bps,
+            Unit::BPS_PRECISION,
+        ))
+    }
+
+    /// `collateral_price` is the value of one unit of collateral in peg asset
+    pub fn calc_mint(
+        &self,
+        collateral_amount: Uint128,
+        collateral_price: Uint128,
+    ) -> CalcMintResult {
         let precision = Unit::precision();
         let buy_share_value = collateral_amount * (precision - self.collateral_ratio) / precision;
-        let synth_out = collateral_amount * (precision - self.minting_fee) / precision;
+        let collateral_value = CollateralInfo::to_value(collateral_amount, collateral_price);
+        let synth_out = collateral_value * (precision - self.minting_fee) / precision;
         let fee =
             collateral_amount * self.collateral_ratio * self.minting_fee / precision / precision;
         CalcMintResult {
@@ -99,13 +200,18 @@ impl PoolConfig {
     }
 
     /// return collateral amount, share amount, and fee amount
-    pub fn calc_redeem(&self, synth_amount: Uint128, share_price: Uint128) -> CalcRedeemResult {
+    pub fn calc_redeem(
+        &self,
+        synth_amount: Uint128,
+        share_price: Uint128,
+        collateral_price: Uint128,
+    ) -> CalcRedeemResult {
         let precision = Unit::precision();
         let collateral_out =
             synth_amount * self.collateral_ratio * (precision - self.redemption_fee)
                 / precision
-                / precision;
-        let fee = synth_amount * self.redemption_fee / precision;
+                / collateral_price;
+        let fee = synth_amount * self.redemption_fee / collateral_price;
 
         let share_out =
             synth_amount * (precision - self.redemption_fee) * (precision - self.collateral_ratio)
@@ -121,43 +227,52 @@ impl PoolConfig {
 
     pub fn mint(
         &mut self,
+        collateral: &mut CollateralInfo,
         user: &mut UserInfo,
         block_height: u64,
         collateral_amount: Uint128,
+        collateral_price: Uint128,
     ) -> CalcMintResult {
-        let result = self.calc_mint(collateral_amount);
+        let result = self.calc_mint(collateral_amount, collateral_price);
         self.total_unclaimed_synth += result.synth_out;
-        self.total_fee += result.fee;
+        collateral.total_fee += result.fee;
         user.last_action_block = block_height;
         user.synth_balance += result.synth_out;
         result
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn redeem(
         &mut self,
+        collateral: &mut CollateralInfo,
         user: &mut UserInfo,
         block_height: u64,
         synth_amount: Uint128,
         share_price: Uint128,
+        collateral_price: Uint128,
     ) -> CalcRedeemResult {
-        let result = self.calc_redeem(synth_amount, share_price);
-        self.total_unclaimed_collateral += result.collateral_out;
+        let result = self.calc_redeem(synth_amount, share_price, collateral_price);
+        collateral.total_unclaimed += result.collateral_out;
         self.total_unclaimed_share += result.share_out;
-        self.total_fee += result.fee;
+        self.total_redeemed_synth += synth_amount;
+        collateral.total_fee += result.fee;
         user.last_action_block = block_height;
         user.share_balance = user.share_balance + result.share_out;
-        user.collateral_balance = user.collateral_balance + result.collateral_out;
+        add_asset(
+            &mut user.collateral_balance,
+            &collateral.asset,
+            result.collateral_out,
+        );
         result
     }
 
+    /// collateral unclaimed total must be reduced by the caller, per collateral
     pub fn collect(&mut self, user: &mut UserInfo) -> CollectResult {
         let synth_amount = user.synth_balance;
         let share_amount = user.share_balance;
-        let collateral_amount = user.collateral_balance;
-        self.total_unclaimed_collateral -= collateral_amount;
+        let collateral_amount = std::mem::take(&mut user.collateral_balance);
         self.total_unclaimed_share -= share_amount;
         self.total_unclaimed_synth -= synth_amount;
-        user.collateral_balance = Uint128::zero();
         user.share_balance = Uint128::zero();
         user.synth_balance = Uint128::zero();
 
@@ -168,15 +283,128 @@ impl PoolConfig {
         }
     }
 
+    /// validate and apply the collateral policy, fields left to None are kept.
+    /// Return an event per changed field
+    pub fn set_collateral_policy(
+        &mut self,
+        price_band: Option<Uint128>,
+        collateral_ratio_step: Option<Uint128>,
+        refresh_collateral_ratio_cooldown: Option<u64>,
+    ) -> Result<Vec<Event>, ContractError> {
+        let mut events: Vec<Event> = vec![];
+
+        if let Some(price_band) = price_band {
+            if price_band >= Unit::precision() {
+                return Err(ContractError::InvalidCollateralPolicy {
+                    msg: String::from("Price band must be lower than 1"),
+                });
+            }
+            if price_band != self.price_band {
+                events.push(
+                    Event::new("update_price_band")
+                        .add_attribute("old", self.price_band)
+                        .add_attribute("new", price_band),
+                );
+                self.price_band = price_band;
+            }
+        }
+
+        if let Some(step) = collateral_ratio_step {
+            if step.is_zero() {
+                return Err(ContractError::InvalidCollateralPolicy {
+                    msg: String::from("Collateral ratio step must be greater than zero"),
+                });
+            }
+            if step != self.collateral_ratio_step {
+                events.push(
+                    Event::new("update_collateral_ratio_step")
+                        .add_attribute("old", self.collateral_ratio_step)
+                        .add_attribute("new", step),
+                );
+                self.collateral_ratio_step = step;
+            }
+        }
+
+        if let Some(cooldown) = refresh_collateral_ratio_cooldown {
+            if cooldown == 0 {
+                return Err(ContractError::InvalidCollateralPolicy {
+                    msg: String::from("Refresh cooldown must be greater than zero"),
+                });
+            }
+            if cooldown != self.refresh_collateral_ratio_cooldown {
+                events.push(
+                    Event::new("update_refresh_collateral_ratio_cooldown")
+                        .add_attribute("old", self.refresh_collateral_ratio_cooldown.to_string())
+                        .add_attribute("new", cooldown.to_string()),
+                );
+                self.refresh_collateral_ratio_cooldown = cooldown;
+            }
+        }
+
+        Ok(events)
+    }
+
+    /// share price for redemption/recollateralize (`pays_out`) or buyback
+    pub fn get_share_price(
+        &self,
+        storage: &dyn Storage,
+        querier: &QuerierWrapper,
+        pays_out: bool,
+        now: u64,
+    ) -> Result<Uint128, ContractError> {
+        SHARE_ORACLE.get_price(
+            storage,
+            querier,
+            &self.share_price_mode,
+            pays_out,
+            now,
+            self.max_twap_age,
+        )
+    }
+
+    /// collateral value the pool should hold to back `synth_supply` at current ratio
+    pub fn target_collateral_value(&self, synth_supply: Uint128) -> Uint128 {
+        synth_supply * self.collateral_ratio / Unit::precision()
+    }
+
+    /// share paid, bonus included, for `collateral_value` of collateral
+    pub fn calc_recollateralize(&self, collateral_value: Uint128, share_price: Uint128) -> Uint128 {
+        collateral_value * (Unit::precision() + self.recollateralize_bonus) / share_price
+    }
+
+    /// collateral value paid, fee excluded, for `share_amount` of share
+    pub fn calc_buyback(&self, share_amount: Uint128, share_price: Uint128) -> Uint128 {
+        let precision = Unit::precision();
+        share_amount * share_price / precision * (precision - self.buyback_fee) / precision
+    }
+
     pub fn refresh_collateral_ratio(&mut self, synth_twap: Uint128) {
         let mut collateral_ratio = self.collateral_ratio;
         let target_price = Unit::precision();
         let max_collateral_ratio = Unit::precision();
 
-        if synth_twap > target_price + self.price_band {
-            collateral_ratio = collateral_ratio - self.collateral_ratio_step
-        } else if synth_twap < target_price - self.price_band {
-            collateral_ratio = collateral_ratio + self.collateral_ratio_step
+        match self.ratio_controller {
+            RatioController::FixedStep => {
+                if synth_twap > target_price + self.price_band {
+                    collateral_ratio = collateral_ratio.saturating_sub(self.collateral_ratio_step)
+                } else if synth_twap < target_price - self.price_band {
+                    collateral_ratio = collateral_ratio + self.collateral_ratio_step
+                }
+            }
+            RatioController::Pid {
+                kp,
+                ki,
+                kd,
+                integral_limit,
+            } => {
+                let step = self.pid_state.step(synth_twap, kp, ki, kd, integral_limit);
+                collateral_ratio = apply_step(
+                    collateral_ratio,
+                    step,
+                    self.min_collateral_ratio,
+                    max_collateral_ratio,
+                );
+            }
         }
 
         self.collateral_ratio =
@@ -184,9 +412,26 @@ impl PoolConfig {
     }
 }
 
+/// one collateral ratio refresh
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CollateralRatioRecord {
+    pub id: u64,
+    pub timestamp: u64,
+    /// synth TWAP the refresh was based on
+    pub synth_twap: Uint128,
+    pub old_collateral_ratio: Uint128,
+    pub new_collateral_ratio: Uint128,
+}
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+/// max users read by one listing query
+const MAX_SCAN: usize = 300;
+
 pub struct Pool<'a> {
     pub pool: Item<'a, PoolConfig>,
     pub user: Map<'a, &'a Addr, UserInfo>,
+    pub ratio_history: Map<'a, U64Key, CollateralRatioRecord>,
 }
 
 impl<'a> Pool<'a> {
@@ -194,6 +439,7 @@ impl<'a> Pool<'a> {
         Self {
             pool: Item::new("pool"),
             user: Map::new("user"),
+            ratio_history: Map::new("ratio_history"),
         }
     }
 
@@ -202,20 +448,24 @@ impl<'a> Pool<'a> {
         &self,
         storage: &mut dyn Storage,
         collateral_denom: String,
+        router: Addr,
+        swap_route: Vec<AssetInfo>,
     ) -> Result<(), ContractError> {
-        let pool = PoolConfig::init(collateral_denom);
+        let pool = PoolConfig::init(collateral_denom, router, swap_route);
         self.pool.save(storage, &pool)?;
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn mint(
         &self,
         storage: &mut dyn Storage,
         querier: &QuerierWrapper,
         env: Env,
         sender: &Addr,
-        funds: Vec<Coin>,
+        collateral_input: Asset,
         min_synth_out: Uint128,
+        min_share_bought: Option<Uint128>,
     ) -> Result<Response, ContractError> {
         let block_height = env.block.height;
         let mut pool = self.get_pool(storage)?;
@@ -224,21 +474,36 @@ impl<'a> Pool<'a> {
             return Err(ContractError::MintingPaused {});
         }
 
-        let collateral_in = funds
-            .iter()
-            .find(|&x| x.denom == pool.collateral_denom) // coin = {denom, amount} // ulunu
-            .map(|x| x.amount)
-            .unwrap_or(Uint128::zero());
+        let Asset {
+            info: collateral_asset,
+            amount: collateral_in,
+        } = collateral_input;
 
         if collateral_in.is_zero() {
             return Err(ContractError::MintInvalidCollateralAmount {});
         }
 
+        SHARE_ORACLE.assert_deviation(storage, querier, env.block.time.seconds())?;
+        SYNTH_ORACLE.assert_deviation(storage, querier, env.block.time.seconds())?;
+
+        let mut collateral = COLLATERALS.get_enabled(storage, &collateral_asset)?;
+        if let Some(oracle) = collateral.oracle() {
+            oracle.assert_deviation(storage, querier, env.block.time.seconds())?;
+        }
+        let collateral_price =
+            collateral.get_price(storage, env.block.time.seconds(), pool.max_twap_age)?;
+
         let CalcMintResult {
             synth_out,
             buy_share_value,
             fee,
-        } = pool.mint(&mut user, block_height, collateral_in);
+        } = pool.mint(
+            &mut collateral,
+            &mut user,
+            block_height,
+            collateral_in,
+            collateral_price,
+        );
 
         if synth_out < min_synth_out {
             return Err(ContractError::SlippageReached {});
@@ -246,45 +511,83 @@ impl<'a> Pool<'a> {
 
         EPOCH.assert_mint_amount(storage, querier, &pool, synth_out, env.block.time.seconds())?;
 
+        let min_share_out = if buy_share_value.is_zero() {
+            Uint128::zero()
+        } else {
+            let (share_twap, _) = SHARE_ORACLE.get_twap(storage)?;
+            let buy_share_peg_value = CollateralInfo::to_value(buy_share_value, collateral_price);
+            pool.calc_min_share_bought(buy_share_peg_value, share_twap)?
+                .max(min_share_bought.unwrap_or_default())
+        };
+
         self.user.save(storage, sender, &user)?;
         self.pool.save(storage, &pool)?;
-
-        let msgs = Pool::buy_share_and_burn(&env.contract.address, &pool, buy_share_value)?;
+        COLLATERALS.save(storage, &collateral)?;
+        COLLATERALS.assert_weight_cap(
+            storage,
+            querier,
+            &env.contract.address,
+            &collateral.asset,
+            buy_share_value,
+            env.block.time.seconds(),
+            pool.max_twap_age,
+        )?;
+
+        let msgs = Pool::buy_share_and_burn(
+            &env.contract.address,
+            &pool,
+            &collateral,
+            buy_share_value,
+            min_share_out,
+        )?;
         Ok(Response::new()
             .add_attribute("action", "mint")
+            .add_attribute("collateral", collateral.asset.to_string())
             .add_attribute("input", collateral_in)
             .add_attribute("output", synth_out)
             .add_attribute("fee", fee)
             .add_attribute("buy_share_value", buy_share_value)
+            .add_attribute("min_share_out", min_share_out)
             .add_messages(msgs))
     }
 
     fn buy_share_and_burn(
         this_addr: &Addr,
         pool: &PoolConfig,
+        collateral: &CollateralInfo,
         buy_share_value: Uint128,
+        min_share_out: Uint128,
     ) -> StdResult<Vec<WasmMsg>> {
         let mut messages: Vec<WasmMsg> = vec![];
         if !buy_share_value.is_zero() {
-            let denom = pool.collateral_denom.clone();
-            messages.push(WasmMsg::Execute {
-                contract_addr: ASTROPORT_ROUTER.into(),
-                msg: to_binary(&AstroportRouterExecuteMsg::ExecuteSwapOperations {
-                    operations: vec![SwapOperation::AstroSwap {
-                        offer_asset_info: AssetInfo::NativeToken {
-                            denom: pool.collateral_denom.clone(),
-                        },
-                        ask_asset_info: AssetInfo::Token {
-                            contract_addr: pool.share.clone(),
-                        },
+            let operations = pool.swap_operations(collateral);
+            messages.push(match &collateral.asset {
+                AssetInfo::NativeToken { denom } => WasmMsg::Execute {
+                    contract_addr: pool.router.to_string(),
+                    msg: to_binary(&AstroportRouterExecuteMsg::ExecuteSwapOperations {
+                        operations,
+                        minimum_receive: Some(min_share_out),
+                        to: Some(this_addr.clone()),
+                    })?,
+                    funds: vec![Coin {
+                        denom: denom.clone(),
+                        amount: buy_share_value,
                     }],
-                    minimum_receive: None,
-                    to: Some(this_addr.clone()),
-                })?,
-                funds: vec![Coin {
-                    denom,
-                    amount: buy_share_value,
-                }],
+                },
+                // cw20 is sent to the router with a swap hook
+                AssetInfo::Token { contract_addr } => WasmMsg::Execute {
+                    contract_addr: contract_addr.to_string(),
+                    msg: to_binary(&Cw20ExecuteMsg::Send {
+                        contract: pool.router.to_string(),
+                        amount: buy_share_value,
+                        msg: to_binary(&AstroportRouterCw20HookMsg::ExecuteSwapOperations {
+                            operations,
+                            minimum_receive: Some(min_share_out),
+                            to: Some(this_addr.to_string()),
+                        })?,
+                    })?,
+                    funds: vec![],
+                },
             });
             messages.push(WasmMsg::Execute {
                 contract_addr: this_addr.to_string(),
@@ -295,6 +598,7 @@ impl<'a> Pool<'a> {
         Ok(messages)
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn redeem(
         &self,
         storage: &mut dyn Storage,
@@ -302,6 +606,7 @@ impl<'a> Pool<'a> {
         env: Env,
         sender: &Addr,
         synth_input: Cw20CoinVerified,
+        collateral: Option<AssetInfo>,
         min_collateral_out: Uint128,
         min_share_out: Uint128,
     ) -> Result<Response, ContractError> {
@@ -324,13 +629,40 @@ impl<'a> Pool<'a> {
         if synth_amount.is_zero() {
             return Err(ContractError::RedeemEmptyAmount {});
         }
-        let share_price = SHARE_ORACLE.get_spot_price(storage, &querier)?;
 
+        let collateral_asset = collateral.unwrap_or_else(|| AssetInfo::NativeToken {
+            denom: pool.collateral_denom.clone(),
+        });
+        SHARE_ORACLE.assert_deviation(storage, querier, env.block.time.seconds())?;
+        SYNTH_ORACLE.assert_deviation(storage, querier, env.block.time.seconds())?;
+
+        let mut collateral = COLLATERALS.get(storage, &collateral_asset)?;
+        if let Some(oracle) = collateral.oracle() {
+            oracle.assert_deviation(storage, querier, env.block.time.seconds())?;
+        }
+        let collateral_price =
+            collateral.get_price(storage, env.block.time.seconds(), pool.max_twap_age)?;
+        let share_price = pool.get_share_price(storage, querier, true, env.block.time.seconds())?;
+
+        let collateral_holding = collateral.get_holding(querier, &env.contract.address)?;
         let CalcRedeemResult {
             collateral_out,
             share_out,
             fee,
-        } = pool.redeem(&mut user, env.block.height, synth_amount, share_price);
+        } = pool.redeem(
+            &mut collateral,
+            &mut user,
+            env.block.height,
+            synth_amount,
+            share_price,
+            collateral_price,
+        );
+
+        if collateral_out + fee > collateral_holding {
+            return Err(ContractError::InsufficientCollateral {
+                asset: collateral_asset.to_string(),
+            });
+        }
 
         if collateral_out < min_collateral_out || share_out < min_share_out {
             return Err(ContractError::SlippageReached {});
@@ -338,9 +670,11 @@ impl<'a> Pool<'a> {
 
         self.pool.save(storage, &pool)?;
         self.user.save(storage, sender, &user)?;
+        COLLATERALS.save(storage, &collateral)?;
 
         Ok(Response::new()
             .add_attribute("action", "redeem")
+            .add_attribute("collateral", collateral_asset.to_string())
             .add_attribute("input", synth_amount)
             .add_attribute("share_out", share_out)
             .add_attribute("collateral_out", collateral_out)
@@ -393,21 +727,341 @@ impl<'a> Pool<'a> {
             }))
         }
 
-        if !collateral_amount.is_zero() {
-            messages.push(CosmosMsg::Bank(BankMsg::Send {
-                to_address: sender.to_string(),
-                amount: vec![Coin::new(collateral_amount.into(), &pool.collateral_denom)],
-            }))
+        for Asset { info, amount } in &collateral_amount {
+            let mut collateral = COLLATERALS.get(storage, info)?;
+            collateral.total_unclaimed -= *amount;
+            COLLATERALS.save(storage, &collateral)?;
+
+            if !amount.is_zero() {
+                messages.push(transfer_asset(info, *amount, sender)?);
+            }
         }
 
+        let collateral_attr = collateral_amount
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<String>>()
+            .join(",");
+
         Ok(Response::new()
             .add_attribute("action", "collect")
-            .add_attribute("collateral_amount", collateral_amount)
+            .add_attribute("collateral_amount", collateral_attr)
             .add_attribute("share_amount", share_amount)
             .add_attribute("synth_amount", synth_amount)
             .add_messages(messages))
     }
 
+    /// compare the value of collateral held with the one required at current ratio
+    pub fn collateral_status(
+        &self,
+        storage: &dyn Storage,
+        querier: &QuerierWrapper,
+        this_addr: &Addr,
+        now: u64,
+    ) -> Result<CollateralValueResponse, ContractError> {
+        let pool = self.get_pool(storage)?;
+        // synth held by the pool is redeemed, synth not collected yet is minted
+        let synth_supply = TokenUtils::query_supply(querier, &pool.synth)?
+            - TokenUtils::query_balance(querier, &pool.synth, this_addr)?
+            + pool.total_unclaimed_synth;
+        let global_collateral_value =
+            COLLATERALS.total_value(storage, querier, this_addr, now, pool.max_twap_age)?;
+        let target_collateral_value = pool.target_collateral_value(synth_supply);
+
+        Ok(CollateralValueResponse {
+            synth_supply,
+            global_collateral_value,
+            target_collateral_value,
+            deficit: target_collateral_value.saturating_sub(global_collateral_value),
+            excess: global_collateral_value.saturating_sub(target_collateral_value),
+        })
+    }
+
+    /// add a page of user ledgers to `user_sums` and check the pool holds
+    /// what it owes. The unclaimed totals are compared with the user sums
+    /// once the last page is read, balances are checked on every page
+    pub fn check_invariants(
+        &self,
+        storage: &dyn Storage,
+        querier: &QuerierWrapper,
+        this_addr: &Addr,
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+        user_sums: Option<UserLedgerSums>,
+    ) -> StdResult<InvariantsResponse> {
+        let pool = self.get_pool(storage)?;
+        let mut discrepancies = vec![];
+
+        let limit = limit.map_or(MAX_SCAN, |x| (x as usize).clamp(1, MAX_SCAN));
+        let start = start_after.map(|x| Bound::exclusive(x.as_bytes()));
+        let mut range = self.user.range(storage, start, None, Order::Ascending);
+
+        let mut sums = user_sums.unwrap_or_default();
+        let mut next_start_after = None;
+        for _ in 0..limit {
+            let (key, user) = match range.next() {
+                Some(item) => item?,
+                None => break,
+            };
+            sums.users_checked += 1;
+            sums.synth_balance += user.synth_balance;
+            sums.share_balance += user.share_balance;
+            for Asset { info, amount } in &user.collateral_balance {
+                add_asset(&mut sums.collateral_balance, info, *amount);
+            }
+            next_start_after = Some(Addr::unchecked(String::from_utf8(key)?));
+        }
+        if range.next().is_none() {
+            next_start_after = None;
+        }
+
+        let collaterals = COLLATERALS.list(storage)?;
+        if next_start_after.is_none() {
+            if sums.synth_balance != pool.total_unclaimed_synth {
+                discrepancies.push(Discrepancy {
+                    check: InvariantCheck::UnclaimedSynth,
+                    asset: None,
+                    expected: pool.total_unclaimed_synth,
+                    actual: sums.synth_balance,
+                });
+            }
+            if sums.share_balance != pool.total_unclaimed_share {
+                discrepancies.push(Discrepancy {
+                    check: InvariantCheck::UnclaimedShare,
+                    asset: None,
+                    expected: pool.total_unclaimed_share,
+                    actual: sums.share_balance,
+                });
+            }
+
+            for collateral in &collaterals {
+                let unclaimed = sums
+                    .collateral_balance
+                    .iter()
+                    .find(|x| x.info.equal(&collateral.asset))
+                    .map_or(Uint128::zero(), |x| x.amount);
+                if unclaimed != collateral.total_unclaimed {
+                    discrepancies.push(Discrepancy {
+                        check: InvariantCheck::UnclaimedCollateral,
+                        asset: Some(collateral.asset.clone()),
+                        expected: collateral.total_unclaimed,
+                        actual: unclaimed,
+                    });
+                }
+            }
+
+            // user collateral no longer in the registry is owed as well
+            for Asset { info, amount } in &sums.collateral_balance {
+                if !collaterals.iter().any(|x| x.asset.equal(info)) && !amount.is_zero() {
+                    discrepancies.push(Discrepancy {
+                        check: InvariantCheck::UnclaimedCollateral,
+                        asset: Some(info.clone()),
+                        expected: Uint128::zero(),
+                        actual: *amount,
+                    });
+                }
+            }
+        }
+
+        for collateral in collaterals {
+            let owed =
+                collateral.total_fee + collateral.total_unclaimed + collateral.total_reserved;
+            let balance = collateral.asset.query_pool(querier, this_addr.clone())?;
+            if balance < owed {
+                discrepancies.push(Discrepancy {
+                    check: InvariantCheck::CollateralBalance,
+                    asset: Some(collateral.asset),
+                    expected: owed,
+                    actual: balance,
+                });
+            }
+        }
+
+        let share_reserved = KEEPER.get_reserved(
+            storage,
+            &AssetInfo::Token {
+                contract_addr: pool.share.clone(),
+            },
+        )?;
+        let share_held = TokenUtils::query_balance(querier, &pool.share, this_addr)?;
+        if share_held < share_reserved {
+            discrepancies.push(Discrepancy {
+                check: InvariantCheck::KeeperShareBudget,
+                asset: None,
+                expected: share_reserved,
+                actual: share_held,
+            });
+        }
+
+        let synth_held = TokenUtils::query_balance(querier, &pool.synth, this_addr)?;
+        if synth_held < pool.total_redeemed_synth {
+            discrepancies.push(Discrepancy {
+                check: InvariantCheck::RedeemedSynth,
+                asset: None,
+                expected: pool.total_redeemed_synth,
+                actual: synth_held,
+            });
+        }
+
+        Ok(InvariantsResponse {
+            user_sums: sums,
+            next_start_after,
+            synth_supply: TokenUtils::query_supply(querier, &pool.synth)?,
+            share_supply: TokenUtils::query_supply(querier, &pool.share)?,
+            synth_held,
+            discrepancies,
+        })
+    }
+
+    /// deposit collateral when the pool is under-collateralized, paid in share with a bonus
+    pub fn recollateralize(
+        &self,
+        storage: &mut dyn Storage,
+        querier: &QuerierWrapper,
+        env: Env,
+        sender: &Addr,
+        collateral_input: Asset,
+        min_share_out: Uint128,
+    ) -> Result<Response, ContractError> {
+        let pool = self.get_pool(storage)?;
+        if pool.recollateralize_paused {
+            return Err(ContractError::RecollateralizePaused {});
+        }
+
+        let Asset {
+            info: collateral_asset,
+            amount: collateral_in,
+        } = collateral_input;
+        if collateral_in.is_zero() {
+            return Err(ContractError::MintInvalidCollateralAmount {});
+        }
+
+        let collateral = COLLATERALS.get_enabled(storage, &collateral_asset)?;
+        let collateral_price =
+            collateral.get_price(storage, env.block.time.seconds(), pool.max_twap_age)?;
+        let collateral_value = CollateralInfo::to_value(collateral_in, collateral_price);
+
+        // the deposit is already part of the holding
+        let status = self.collateral_status(
+            storage,
+            querier,
+            &env.contract.address,
+            env.block.time.seconds(),
+        )?;
+        if !status.excess.is_zero() {
+            return Err(ContractError::RecollateralizeTooLarge {});
+        }
+        COLLATERALS.assert_weight_cap(
+            storage,
+            querier,
+            &env.contract.address,
+            &collateral_asset,
+            Uint128::zero(),
+            env.block.time.seconds(),
+            pool.max_twap_age,
+        )?;
+
+        let share_price = pool.get_share_price(storage, querier, true, env.block.time.seconds())?;
+        let share_out = pool.calc_recollateralize(collateral_value, share_price);
+        if share_out < min_share_out {
+            return Err(ContractError::SlippageReached {});
+        }
+
+        let msg = WasmMsg::Execute {
+            contract_addr: pool.share.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Mint {
+                recipient: sender.to_string(),
+                amount: share_out,
+            })?,
+            funds: vec![],
+        };
+
+        Ok(Response::new()
+            .add_attribute("action", "recollateralize")
+            .add_attribute("collateral", collateral_asset.to_string())
+            .add_attribute("input", collateral_in)
+            .add_attribute("share_out", share_out)
+            .add_message(msg))
+    }
+
+    /// sell share for the collateral in excess of the current ratio, share is burnt
+    #[allow(clippy::too_many_arguments)]
+    pub fn buyback(
+        &self,
+        storage: &mut dyn Storage,
+        querier: &QuerierWrapper,
+        env: Env,
+        sender: &Addr,
+        share_input: Cw20CoinVerified,
+        collateral: Option<AssetInfo>,
+        min_collateral_out: Uint128,
+    ) -> Result<Response, ContractError> {
+        let pool = self.get_pool(storage)?;
+        if pool.buyback_paused {
+            return Err(ContractError::BuybackPaused {});
+        }
+
+        // important! user can send fake token to trigger this
+        if pool.share != share_input.address {
+            return Err(ContractError::BuybackInvalidShareInput {
+                want: pool.share,
+                send: share_input.address,
+            });
+        }
+        let share_amount = share_input.amount;
+        if share_amount.is_zero() {
+            return Err(ContractError::RedeemEmptyAmount {});
+        }
+
+        let collateral_asset = collateral.unwrap_or_else(|| AssetInfo::NativeToken {
+            denom: pool.collateral_denom.clone(),
+        });
+        let collateral = COLLATERALS.get(storage, &collateral_asset)?;
+        let collateral_price =
+            collateral.get_price(storage, env.block.time.seconds(), pool.max_twap_age)?;
+
+        let share_price =
+            pool.get_share_price(storage, querier, false, env.block.time.seconds())?;
+        let value_out = pool.calc_buyback(share_amount, share_price);
+        let status = self.collateral_status(
+            storage,
+            querier,
+            &env.contract.address,
+            env.block.time.seconds(),
+        )?;
+        if value_out > status.excess {
+            return Err(ContractError::BuybackTooLarge {});
+        }
+
+        let collateral_out = value_out * Unit::precision() / collateral_price;
+        if collateral_out > collateral.get_holding(querier, &env.contract.address)? {
+            return Err(ContractError::InsufficientCollateral {
+                asset: collateral_asset.to_string(),
+            });
+        }
+        if collateral_out < min_collateral_out {
+            return Err(ContractError::SlippageReached {});
+        }
+
+        let messages: Vec<CosmosMsg> = vec![
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: pool.share.to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Burn {
+                    amount: share_amount,
+                })?,
+                funds: vec![],
+            }),
+            transfer_asset(&collateral_asset, collateral_out, sender)?,
+        ];
+
+        Ok(Response::new()
+            .add_attribute("action", "buyback")
+            .add_attribute("collateral", collateral_asset.to_string())
+            .add_attribute("input", share_amount)
+            .add_attribute("collateral_out", collateral_out)
+            .add_messages(messages))
+    }
+
     pub fn refresh_collateral_ratio(
         &self,
         storage: &mut dyn Storage,
@@ -425,15 +1079,96 @@ impl<'a> Pool<'a> {
             return Err(ContractError::PriceUnavailableOrOutdated {});
         }
 
+        let old_collateral_ratio = pool.collateral_ratio;
         pool.refresh_collateral_ratio(synth_twap);
+        pool.last_refresh_collateral_ratio = now;
         self.pool.save(storage, &pool)?;
 
+        let id = self
+            .ratio_history
+            .keys(storage, None, None, Order::Descending)
+            .next()
+            .map_or(0, |key| {
+                u64::from_be_bytes(key.as_slice().try_into().unwrap()) + 1
+            });
+        self.ratio_history.save(
+            storage,
+            U64Key::new(id),
+            &CollateralRatioRecord {
+                id,
+                timestamp: now,
+                synth_twap,
+                old_collateral_ratio,
+                new_collateral_ratio: pool.collateral_ratio,
+            },
+        )?;
+
         Ok(Response::new()
             .add_attribute("action", "refresh_collateral_ratio")
             .add_attribute("collateral_ratio", pool.collateral_ratio)
             .add_attribute("timestamp", now.to_string()))
     }
 
+    /// collateral ratio refreshes in ascending order
+    pub fn get_ratio_history(
+        &self,
+        storage: &dyn Storage,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<Vec<CollateralRatioRecord>> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after.map(Bound::exclusive_int);
+        self.ratio_history
+            .range(storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|item| item.map(|(_, record)| record))
+            .collect()
+    }
+
+    /// users in address order, only those with unclaimed balances when
+    /// `unclaimed_only`. At most `MAX_SCAN` users are read per call, continue
+    /// from `next_start_after` while it is set
+    pub fn get_users(
+        &self,
+        storage: &dyn Storage,
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+        unclaimed_only: bool,
+    ) -> StdResult<UsersResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after.map(|x| Bound::exclusive(x.as_bytes()));
+        let mut range = self.user.range(storage, start, None, Order::Ascending);
+
+        let mut users = vec![];
+        let mut last_scanned = None;
+        let mut scanned = 0;
+        while users.len() < limit && scanned < MAX_SCAN {
+            let (key, info) = match range.next() {
+                Some(item) => item?,
+                None => {
+                    return Ok(UsersResponse {
+                        users,
+                        next_start_after: None,
+                    })
+                }
+            };
+            scanned += 1;
+            let address = Addr::unchecked(String::from_utf8(key)?);
+            if !unclaimed_only || info.has_unclaimed() {
+                users.push(UserEntry {
+                    address: address.clone(),
+                    info,
+                });
+            }
+            last_scanned = Some(address);
+        }
+
+        Ok(UsersResponse {
+            users,
+            next_start_after: last_scanned,
+        })
+    }
+
     // ======== Admin function ========
     pub fn set_fee(
         &self,
@@ -459,18 +1194,150 @@ impl<'a> Pool<'a> {
         storage: &mut dyn Storage,
         mint_paused: bool,
         redeem_paused: bool,
+        recollateralize_paused: Option<bool>,
+        buyback_paused: Option<bool>,
     ) -> Result<Response, ContractError> {
-        self.pool
+        let state = self
+            .pool
             .update(storage, |mut state| -> Result<_, ContractError> {
                 state.mint_paused = mint_paused;
                 state.redeem_paused = redeem_paused;
+                state.recollateralize_paused =
+                    recollateralize_paused.unwrap_or(state.recollateralize_paused);
+                state.buyback_paused = buyback_paused.unwrap_or(state.buyback_paused);
                 Ok(state)
             })?;
 
         Ok(Response::new()
             .add_attribute("action", "transfer_ownership")
             .add_attribute("mint_paused", mint_paused.to_string())
-            .add_attribute("redeem_paused", redeem_paused.to_string()))
+            .add_attribute("redeem_paused", redeem_paused.to_string())
+            .add_attribute(
+                "recollateralize_paused",
+                state.recollateralize_paused.to_string(),
+            )
+            .add_attribute("buyback_paused", state.buyback_paused.to_string()))
+    }
+
+    pub fn set_share_pricing(
+        &self,
+        storage: &mut dyn Storage,
+        mode: PriceMode,
+        max_twap_age: u64,
+    ) -> Result<Response, ContractError> {
+        self.pool
+            .update(storage, |mut state| -> Result<_, ContractError> {
+                state.share_price_mode = mode.clone();
+                state.max_twap_age = max_twap_age;
+                Ok(state)
+            })?;
+
+        Ok(Response::new()
+            .add_attribute("action", "set_share_pricing")
+            .add_attribute("mode", format!("{:?}", mode))
+            .add_attribute("max_twap_age", max_twap_age.to_string()))
+    }
+
+    pub fn set_recollateralize_config(
+        &self,
+        storage: &mut dyn Storage,
+        recollateralize_bonus: Uint128,
+        buyback_fee: Uint128,
+    ) -> Result<Response, ContractError> {
+        if recollateralize_bonus > Unit::precision() || buyback_fee > Unit::precision() {
+            return Err(ContractError::Std(StdError::generic_err(
+                "Bonus and fee cannot exceed 100%",
+            )));
+        }
+        self.pool
+            .update(storage, |mut state| -> Result<_, ContractError> {
+                state.recollateralize_bonus = recollateralize_bonus;
+                state.buyback_fee = buyback_fee;
+                Ok(state)
+            })?;
+
+        Ok(Response::new()
+            .add_attribute("action", "set_recollateralize_config")
+            .add_attribute("recollateralize_bonus", recollateralize_bonus)
+            .add_attribute("buyback_fee", buyback_fee))
+    }
+
+    pub fn set_router(
+        &self,
+        storage: &mut dyn Storage,
+        router: Addr,
+        swap_route: Vec<AssetInfo>,
+    ) -> Result<Response, ContractError> {
+        self.pool
+            .update(storage, |mut state| -> Result<_, ContractError> {
+                state.router = router.clone();
+                state.swap_route = swap_route;
+                Ok(state)
+            })?;
+
+        Ok(Response::new()
+            .add_attribute("action", "set_router")
+            .add_attribute("router", router))
+    }
+
+    pub fn set_buyback_slippage(
+        &self,
+        storage: &mut dyn Storage,
+        max_slippage_bps: u64,
+    ) -> Result<Response, ContractError> {
+        if max_slippage_bps > Unit::BPS_PRECISION {
+            return Err(ContractError::Std(StdError::generic_err(
+                "Max slippage cannot exceed 10000 bps",
+            )));
+        }
+        self.pool
+            .update(storage, |mut state| -> Result<_, ContractError> {
+                state.buyback_max_slippage_bps = max_slippage_bps;
+                Ok(state)
+            })?;
+
+        Ok(Response::new()
+            .add_attribute("action", "set_buyback_slippage")
+            .add_attribute("max_slippage_bps", max_slippage_bps.to_string()))
+    }
+
+    pub fn update_collateral_policy(
+        &self,
+        storage: &mut dyn Storage,
+        price_band: Option<Uint128>,
+        collateral_ratio_step: Option<Uint128>,
+        refresh_collateral_ratio_cooldown: Option<u64>,
+    ) -> Result<Response, ContractError> {
+        let mut pool = self.get_pool(storage)?;
+        let events = pool.set_collateral_policy(
+            price_band,
+            collateral_ratio_step,
+            refresh_collateral_ratio_cooldown,
+        )?;
+        self.pool.save(storage, &pool)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "update_collateral_policy")
+            .add_events(events))
+    }
+
+    /// switch collateral ratio controller, PID memory is reset
+    pub fn set_ratio_controller(
+        &self,
+        storage: &mut dyn Storage,
+        controller: RatioController,
+    ) -> Result<Response, ContractError> {
+        controller.validate()?;
+        self.pool
+            .update(storage, |mut state| -> Result<_, ContractError> {
+                state.ratio_controller = controller.clone();
+                state.pid_state = PidState::default();
+                Ok(state)
+            })?;
+
+        Ok(Response::new()
+            .add_attribute("action", "set_ratio_controller")
+            .add_attribute("controller", format!("{:?}", controller)))
     }
 
     pub fn set_min_collateral_ratio(
@@ -540,3 +1407,472 @@ impl<'a> Pool<'a> {
 }
 
 pub const POOL: Pool = Pool::new();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collateral::CollateralPrice;
+    use crate::oracle::{OracleSource, PriceSource};
+    use cosmwasm_std::testing::{mock_env, MockStorage};
+    use cosmwasm_std::{
+        from_binary, from_slice, BalanceResponse as BankBalanceResponse, BankMsg, BankQuery,
+        ContractResult, Empty, Querier, QuerierResult, QueryRequest, SystemResult, WasmQuery,
+    };
+    use cw20::{BalanceResponse, Cw20QueryMsg, TokenInfoResponse};
+
+    /// answers cw20 balance and supply queries with fixed amounts, and the
+    /// native collateral balance with `collateral`
+    struct TokenQuerier {
+        balance: Uint128,
+        supply: Uint128,
+        collateral: Uint128,
+    }
+
+    impl Querier for TokenQuerier {
+        fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+            let msg = match from_slice(bin_request).unwrap() {
+                QueryRequest::<Empty>::Wasm(WasmQuery::Smart { msg, .. }) => msg,
+                QueryRequest::Bank(BankQuery::Balance { denom, .. }) => {
+                    return SystemResult::Ok(ContractResult::Ok(
+                        to_binary(&BankBalanceResponse {
+                            amount: Coin {
+                                denom,
+                                amount: self.collateral,
+                            },
+                        })
+                        .unwrap(),
+                    ))
+                }
+                _ => panic!("unexpected query"),
+            };
+            let res = match from_slice(&msg).unwrap() {
+                Cw20QueryMsg::Balance { .. } => to_binary(&BalanceResponse {
+                    balance: self.balance,
+                }),
+                Cw20QueryMsg::TokenInfo {} => to_binary(&TokenInfoResponse {
+                    name: "token".to_string(),
+                    symbol: "TKN".to_string(),
+                    decimals: 6,
+                    total_supply: self.supply,
+                }),
+                _ => panic!("unexpected query"),
+            };
+            SystemResult::Ok(ContractResult::Ok(res.unwrap()))
+        }
+    }
+
+    fn save_users(storage: &mut dyn Storage, count: usize, unclaimed_every: usize) {
+        for i in 0..count {
+            let info = UserInfo {
+                synth_balance: if i % unclaimed_every == 0 {
+                    Uint128::new(1)
+                } else {
+                    Uint128::zero()
+                },
+                ..UserInfo::default()
+            };
+            POOL.user
+                .save(storage, &Addr::unchecked(format!("user{:04}", i)), &info)
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn get_users_pages_with_cursor() {
+        let mut storage = MockStorage::new();
+        save_users(&mut storage, 25, 1);
+
+        let page = POOL.get_users(&storage, None, Some(10), false).unwrap();
+        assert_eq!(page.users.len(), 10);
+        assert_eq!(page.next_start_after, Some(Addr::unchecked("user0009")));
+
+        let page = POOL
+            .get_users(&storage, page.next_start_after, Some(30), false)
+            .unwrap();
+        assert_eq!(page.users.len(), 15);
+        assert_eq!(page.users[0].address, Addr::unchecked("user0010"));
+        assert_eq!(page.next_start_after, None);
+    }
+
+    #[test]
+    fn get_users_caps_scan_when_unclaimed_are_sparse() {
+        let mut storage = MockStorage::new();
+        save_users(&mut storage, MAX_SCAN + 50, 100);
+
+        let page = POOL.get_users(&storage, None, Some(30), true).unwrap();
+        assert_eq!(page.users.len(), 3);
+        assert_eq!(
+            page.next_start_after,
+            Some(Addr::unchecked(format!("user{:04}", MAX_SCAN - 1)))
+        );
+
+        let page = POOL
+            .get_users(&storage, page.next_start_after, Some(30), true)
+            .unwrap();
+        assert_eq!(page.users.len(), 1);
+        assert_eq!(page.users[0].address, Addr::unchecked("user0300"));
+        assert_eq!(page.next_start_after, None);
+    }
+
+    #[test]
+    fn check_invariants_carries_user_sums_across_pages() {
+        let mut storage = MockStorage::new();
+        let mut pool = PoolConfig::init("uusd".to_string(), Addr::unchecked("router"), vec![]);
+        pool.synth = Addr::unchecked("synth");
+        pool.share = Addr::unchecked("share");
+        pool.total_unclaimed_synth = Uint128::new(5);
+        pool.total_redeemed_synth = Uint128::new(100);
+        POOL.pool.save(&mut storage, &pool).unwrap();
+        KEEPER.initialize(&mut storage).unwrap();
+        save_users(&mut storage, 5, 1);
+
+        let querier = TokenQuerier {
+            balance: Uint128::new(100),
+            supply: Uint128::new(1_000),
+            collateral: Uint128::zero(),
+        };
+        let querier = QuerierWrapper::new(&querier);
+        let this_addr = Addr::unchecked("pool");
+
+        let page = POOL
+            .check_invariants(&storage, &querier, &this_addr, None, Some(3), None)
+            .unwrap();
+        assert_eq!(page.user_sums.users_checked, 3);
+        assert_eq!(page.user_sums.synth_balance, Uint128::new(3));
+        assert_eq!(page.next_start_after, Some(Addr::unchecked("user0002")));
+        // the ledger is only compared once every user was summed
+        assert!(page.discrepancies.is_empty());
+
+        let page = POOL
+            .check_invariants(
+                &storage,
+                &querier,
+                &this_addr,
+                page.next_start_after,
+                Some(3),
+                Some(page.user_sums),
+            )
+            .unwrap();
+        assert_eq!(page.user_sums.users_checked, 5);
+        assert_eq!(page.next_start_after, None);
+        assert_eq!(page.synth_held, Uint128::new(100));
+        assert!(page.discrepancies.is_empty());
+
+        // synth held by the pool no longer covers what was redeemed
+        pool.total_redeemed_synth = Uint128::new(101);
+        pool.total_unclaimed_synth = Uint128::new(4);
+        POOL.pool.save(&mut storage, &pool).unwrap();
+        let page = POOL
+            .check_invariants(&storage, &querier, &this_addr, None, None, None)
+            .unwrap();
+        assert_eq!(page.next_start_after, None);
+        assert_eq!(
+            page.discrepancies,
+            vec![
+                Discrepancy {
+                    check: InvariantCheck::UnclaimedSynth,
+                    asset: None,
+                    expected: Uint128::new(4),
+                    actual: Uint128::new(5),
+                },
+                Discrepancy {
+                    check: InvariantCheck::RedeemedSynth,
+                    asset: None,
+                    expected: Uint128::new(101),
+                    actual: Uint128::new(100),
+                },
+            ]
+        );
+    }
+
+    const SYNTH_SUPPLY: u128 = 1_000_000_000;
+    /// collateral required at a 0.8 ratio
+    const TARGET: u128 = 800_000_000;
+
+    /// pool at a 0.8 collateral ratio, 1% recollateralize bonus and 0.5%
+    /// buyback fee, share at 2.0 and collateral pegged
+    fn setup_collateral(storage: &mut dyn Storage) {
+        POOL.initialize(
+            storage,
+            "uusd".to_string(),
+            Addr::unchecked("router"),
+            vec![],
+        )
+        .unwrap();
+        POOL.pool
+            .update(storage, |mut pool| -> StdResult<_> {
+                pool.synth = Addr::unchecked("synth");
+                pool.share = Addr::unchecked("share");
+                pool.collateral_ratio = Uint128::new(800_000);
+                pool.recollateralize_bonus = Uint128::new(10_000);
+                pool.buyback_fee = Uint128::new(5_000);
+                Ok(pool)
+            })
+            .unwrap();
+        COLLATERALS
+            .save(
+                storage,
+                &CollateralInfo::new(uusd(), CollateralPrice::Peg, Unit::precision(), None),
+            )
+            .unwrap();
+
+        let querier = collateral_querier(0);
+        SHARE_ORACLE.initialize(storage).unwrap();
+        SHARE_ORACLE
+            .config(
+                storage,
+                &QuerierWrapper::new(&querier),
+                &[OracleSource {
+                    source: PriceSource::Fixed {
+                        price: Uint128::new(2_000_000),
+                    },
+                    min_liquidity: Uint128::zero(),
+                }],
+                &[],
+                600,
+                mock_env().block.time.seconds(),
+            )
+            .unwrap();
+    }
+
+    fn uusd() -> AssetInfo {
+        AssetInfo::NativeToken {
+            denom: "uusd".to_string(),
+        }
+    }
+
+    fn collateral_querier(collateral: u128) -> TokenQuerier {
+        TokenQuerier {
+            balance: Uint128::zero(),
+            supply: Uint128::new(SYNTH_SUPPLY),
+            collateral: Uint128::new(collateral),
+        }
+    }
+
+    fn recollateralize(
+        storage: &mut dyn Storage,
+        held: u128,
+        amount: u128,
+        min_share_out: u128,
+    ) -> Result<Response, ContractError> {
+        let querier = collateral_querier(held);
+        POOL.recollateralize(
+            storage,
+            &QuerierWrapper::new(&querier),
+            mock_env(),
+            &Addr::unchecked("alice"),
+            Asset {
+                info: uusd(),
+                amount: Uint128::new(amount),
+            },
+            Uint128::new(min_share_out),
+        )
+    }
+
+    fn buyback(
+        storage: &mut dyn Storage,
+        held: u128,
+        share: u128,
+        min_collateral_out: u128,
+    ) -> Result<Response, ContractError> {
+        let querier = collateral_querier(held);
+        POOL.buyback(
+            storage,
+            &QuerierWrapper::new(&querier),
+            mock_env(),
+            &Addr::unchecked("alice"),
+            Cw20CoinVerified {
+                address: Addr::unchecked("share"),
+                amount: Uint128::new(share),
+            },
+            None,
+            Uint128::new(min_collateral_out),
+        )
+    }
+
+    #[test]
+    fn recollateralize_pays_share_with_bonus() {
+        let mut storage = MockStorage::new();
+        setup_collateral(&mut storage);
+
+        // 100 deposited into a pool holding 700 of the 800 required,
+        // 100 × 1.01 / 2.0 share
+        let res = recollateralize(&mut storage, 700_000_000, 100_000_000, 0).unwrap();
+        match &res.messages[0].msg {
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr, msg, ..
+            }) => {
+                assert_eq!(contract_addr, "share");
+                assert_eq!(
+                    from_binary::<Cw20ExecuteMsg>(msg).unwrap(),
+                    Cw20ExecuteMsg::Mint {
+                        recipient: "alice".to_string(),
+                        amount: Uint128::new(50_500_000),
+                    }
+                );
+            }
+            _ => panic!("unexpected message"),
+        }
+
+        let err = recollateralize(&mut storage, 700_000_000, 100_000_000, 50_500_001).unwrap_err();
+        assert!(matches!(err, ContractError::SlippageReached {}));
+    }
+
+    #[test]
+    fn recollateralize_needs_deficit() {
+        let mut storage = MockStorage::new();
+        setup_collateral(&mut storage);
+
+        let err = recollateralize(&mut storage, TARGET + 1, 100_000_000, 0).unwrap_err();
+        assert!(matches!(err, ContractError::RecollateralizeTooLarge {}));
+        // exactly at target is still accepted
+        recollateralize(&mut storage, TARGET, 100_000_000, 0).unwrap();
+
+        POOL.toggle(&mut storage, false, false, Some(true), None)
+            .unwrap();
+        let err = recollateralize(&mut storage, 700_000_000, 100_000_000, 0).unwrap_err();
+        assert!(matches!(err, ContractError::RecollateralizePaused {}));
+    }
+
+    #[test]
+    fn buyback_pays_collateral_minus_fee() {
+        let mut storage = MockStorage::new();
+        setup_collateral(&mut storage);
+
+        // 10 share at 2.0 less the 0.5% fee, out of the 100 in excess
+        let res = buyback(&mut storage, 900_000_000, 10_000_000, 0).unwrap();
+        assert_eq!(res.messages.len(), 2);
+        match &res.messages[0].msg {
+            CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => assert_eq!(
+                from_binary::<Cw20ExecuteMsg>(msg).unwrap(),
+                Cw20ExecuteMsg::Burn {
+                    amount: Uint128::new(10_000_000),
+                }
+            ),
+            _ => panic!("unexpected message"),
+        }
+        assert_eq!(
+            res.messages[1].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "alice".to_string(),
+                amount: vec![Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::new(19_900_000),
+                }],
+            })
+        );
+
+        let err = buyback(&mut storage, 900_000_000, 10_000_000, 19_900_001).unwrap_err();
+        assert!(matches!(err, ContractError::SlippageReached {}));
+    }
+
+    #[test]
+    fn buyback_needs_excess() {
+        let mut storage = MockStorage::new();
+        setup_collateral(&mut storage);
+
+        // 60 share are worth 119.4, over the 100 in excess
+        let err = buyback(&mut storage, 900_000_000, 60_000_000, 0).unwrap_err();
+        assert!(matches!(err, ContractError::BuybackTooLarge {}));
+        let err = buyback(&mut storage, 700_000_000, 10_000_000, 0).unwrap_err();
+        assert!(matches!(err, ContractError::BuybackTooLarge {}));
+
+        POOL.toggle(&mut storage, false, false, None, Some(true))
+            .unwrap();
+        let err = buyback(&mut storage, 900_000_000, 10_000_000, 0).unwrap_err();
+        assert!(matches!(err, ContractError::BuybackPaused {}));
+    }
+
+    #[test]
+    fn mint_buyback_slippage_cap() {
+        let mut storage = MockStorage::new();
+        setup_collateral(&mut storage);
+        let pool = POOL.get_pool(&storage).unwrap();
+
+        // 100 of collateral buys 50 share at 2.0, less 1% slippage
+        let min_share_out = pool
+            .calc_min_share_bought(Uint128::new(100_000_000), Uint128::new(2_000_000))
+            .unwrap();
+        assert_eq!(min_share_out, Uint128::new(49_500_000));
+        assert!(matches!(
+            pool.calc_min_share_bought(Uint128::new(100_000_000), Uint128::zero()),
+            Err(ContractError::PriceUnavailableOrOutdated {})
+        ));
+
+        // the router swap is rejected below the cap
+        let collateral = COLLATERALS.get(&storage, &uusd()).unwrap();
+        let msgs = Pool::buy_share_and_burn(
+            &Addr::unchecked("pool"),
+            &pool,
+            &collateral,
+            Uint128::new(100_000_000),
+            min_share_out,
+        )
+        .unwrap();
+        match &msgs[0] {
+            WasmMsg::Execute { msg, .. } => match from_binary(msg).unwrap() {
+                AstroportRouterExecuteMsg::ExecuteSwapOperations {
+                    minimum_receive, ..
+                } => assert_eq!(minimum_receive, Some(min_share_out)),
+                _ => panic!("unexpected message"),
+            },
+            _ => panic!("unexpected message"),
+        }
+
+        POOL.set_buyback_slippage(&mut storage, 500).unwrap();
+        let pool = POOL.get_pool(&storage).unwrap();
+        assert_eq!(
+            pool.calc_min_share_bought(Uint128::new(100_000_000), Uint128::new(2_000_000))
+                .unwrap(),
+            Uint128::new(47_500_000)
+        );
+        assert!(POOL
+            .set_buyback_slippage(&mut storage, Unit::BPS_PRECISION + 1)
+            .is_err());
+    }
+
+    #[test]
+    fn calc_mint_and_redeem_at_collateral_price() {
+        let mut pool = PoolConfig::init("uusd".to_string(), Addr::unchecked("router"), vec![]);
+        pool.collateral_ratio = Uint128::new(800_000);
+        let amount = Uint128::new(100_000_000);
+        let share_price = Uint128::new(2_000_000);
+
+        // 100 collateral at 1.25 is worth 125, less 0.3% minting fee
+        assert_eq!(
+            pool.calc_mint(amount, Uint128::new(1_250_000)),
+            CalcMintResult {
+                synth_out: Uint128::new(124_625_000),
+                buy_share_value: Uint128::new(20_000_000),
+                fee: Uint128::new(240_000),
+            }
+        );
+        assert_eq!(
+            pool.calc_mint(amount, Uint128::new(800_000)),
+            CalcMintResult {
+                synth_out: Uint128::new(79_760_000),
+                buy_share_value: Uint128::new(20_000_000),
+                fee: Uint128::new(240_000),
+            }
+        );
+
+        // 100 synth less 0.5% redemption fee, 80% paid in collateral at its
+        // price and 20% in share at 2.0
+        assert_eq!(
+            pool.calc_redeem(amount, share_price, Uint128::new(1_250_000)),
+            CalcRedeemResult {
+                collateral_out: Uint128::new(63_680_000),
+                share_out: Uint128::new(9_950_000),
+                fee: Uint128::new(400_000),
+            }
+        );
+        assert_eq!(
+            pool.calc_redeem(amount, share_price, Uint128::new(800_000)),
+            CalcRedeemResult {
+                collateral_out: Uint128::new(99_500_000),
+                share_out: Uint128::new(9_950_000),
+                fee: Uint128::new(625_000),
+            }
+        );
+    }
+}